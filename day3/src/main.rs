@@ -1,5 +1,7 @@
 use std::collections::{HashMap, HashSet};
-use std::fs;
+use std::{env, fs};
+
+mod repack;
 
 #[derive(Debug)]
 struct Rucksack {
@@ -89,11 +91,27 @@ fn part2(input: &str) -> i32 {
 
 fn main() {
     let input = fs::read_to_string("input.txt").unwrap();
-    println!("{:#?}", part1(&input));
-    println!("{:#?}", part2(&input));
+    match env::args().nth(1).as_deref() {
+        Some("repack") => {
+            let repacking = repack::repack(&input);
+            for swap in repacking.swaps.iter() {
+                println!("{}", swap);
+            }
+            for rucksack in repacking.rucksacks.iter() {
+                println!("{}", rucksack);
+            }
+            for i in repacking.unresolved.iter() {
+                println!("rucksack {}: no arrangement without shared items", i + 1);
+            }
+            println!("Verified: {}", repacking.verify());
+        }
+        _ => {
+            println!("{:#?}", part1(&input));
+            println!("{:#?}", part2(&input));
+        }
+    }
 }
 
-
 #[cfg(test)]
 mod tests {
     use crate::*;
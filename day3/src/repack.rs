@@ -0,0 +1,277 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::Rucksack;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Swap {
+    /// Exchange an item of the first compartment with one of the second compartment
+    Compartments {
+        rucksack: usize,
+        left: usize,
+        right: usize,
+        items: (char, char),
+    },
+    /// Exchange items between two rucksacks of the same group
+    Rucksacks {
+        first: (usize, usize),
+        second: (usize, usize),
+        items: (char, char),
+    },
+}
+
+impl fmt::Display for Swap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Swap::Compartments {
+                rucksack,
+                left,
+                right,
+                items: (a, b),
+            } => write!(
+                f,
+                "rucksack {}: swap {}@{} <-> {}@{}",
+                rucksack + 1,
+                a,
+                left,
+                b,
+                right
+            ),
+            Swap::Rucksacks {
+                first: (r1, i1),
+                second: (r2, i2),
+                items: (a, b),
+            } => write!(
+                f,
+                "rucksacks {} <-> {}: swap {}@{} <-> {}@{}",
+                r1 + 1,
+                r2 + 1,
+                a,
+                i1,
+                b,
+                i2
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Repacking {
+    pub swaps: Vec<Swap>,
+    pub rucksacks: Vec<String>,
+    /// Rucksacks for which no arrangement without shared items exists
+    pub unresolved: Vec<usize>,
+}
+
+impl Repacking {
+    /// Check every resolved rucksack with `Rucksack::get_common`
+    pub fn verify(&self) -> bool {
+        self.rucksacks
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.unresolved.contains(i))
+            .all(|(_, r)| Rucksack::new(r).get_common().is_none())
+    }
+}
+
+/// Minimal list of `(left, right)` index pairs to swap so that no item type
+/// ends up in both compartments, or `None` if the item counts can't be split.
+///
+/// Every type has to end up entirely on one side, and the first compartment
+/// has to keep its size, so this is a subset-sum over the item types where the
+/// cost of sending a type to the second compartment is the number of its items
+/// currently in the first one.
+fn plan_compartments(sequence: &str) -> Option<Vec<(usize, usize)>> {
+    let items: Vec<char> = sequence.chars().collect();
+    let half = items.len() / 2;
+    let mut counts: BTreeMap<char, (usize, usize)> = BTreeMap::new();
+    for (i, c) in items.iter().enumerate() {
+        let entry = counts.entry(*c).or_default();
+        if i < half {
+            entry.0 += 1;
+        } else {
+            entry.1 += 1;
+        }
+    }
+    let types: Vec<(char, usize, usize)> = counts.iter().map(|(c, (l, r))| (*c, *l, *r)).collect();
+
+    // dp[i][s]: minimal cost with the first i types placed and s items on the left
+    let mut dp: Vec<Vec<Option<usize>>> = vec![vec![None; half + 1]; types.len() + 1];
+    dp[0][0] = Some(0);
+    for (i, (_, left, right)) in types.iter().enumerate() {
+        for s in 0..=half {
+            let Some(cost) = dp[i][s] else { continue };
+            let keep = &mut dp[i + 1][s];
+            if keep.is_none_or(|c| cost + left < c) {
+                *keep = Some(cost + left);
+            }
+            if s + left + right <= half {
+                let take = &mut dp[i + 1][s + left + right];
+                if take.is_none_or(|c| cost < c) {
+                    *take = Some(cost);
+                }
+            }
+        }
+    }
+    dp[types.len()][half]?;
+
+    let mut on_left: HashSet<char> = HashSet::new();
+    let mut s = half;
+    for i in (0..types.len()).rev() {
+        let (c, left, right) = types[i];
+        let cost = dp[i + 1][s].unwrap();
+        if s >= left + right && dp[i][s - left - right] == Some(cost) {
+            on_left.insert(c);
+            s -= left + right;
+        }
+    }
+
+    let outgoing = (0..half).filter(|i| !on_left.contains(&items[*i]));
+    let incoming = (half..items.len()).filter(|i| on_left.contains(&items[*i]));
+    Some(outgoing.zip(incoming).collect())
+}
+
+fn apply(sequence: &str, pairs: &[(usize, usize)]) -> String {
+    let mut items: Vec<char> = sequence.chars().collect();
+    for (l, r) in pairs {
+        items.swap(*l, *r);
+    }
+    items.into_iter().collect()
+}
+
+/// Exchange the `i`-th item of rucksack `a` with the `j`-th item of rucksack `b`
+fn exchange(group: &mut [String], (a, i): (usize, usize), (b, j): (usize, usize)) -> (char, char) {
+    let mut first: Vec<char> = group[a].chars().collect();
+    let mut second: Vec<char> = group[b].chars().collect();
+    std::mem::swap(&mut first[i], &mut second[j]);
+    group[a] = first.into_iter().collect();
+    group[b] = second.into_iter().collect();
+    (
+        group[b].chars().nth(j).unwrap(),
+        group[a].chars().nth(i).unwrap(),
+    )
+}
+
+fn badge(group: &[String]) -> HashSet<char> {
+    group
+        .iter()
+        .map(|r| Rucksack::new(r).get_set())
+        .reduce(|a, b| a.intersection(&b).copied().collect())
+        .unwrap_or_default()
+}
+
+fn total_cost(group: &[String]) -> Option<usize> {
+    group
+        .iter()
+        .map(|r| plan_compartments(r).map(|p| p.len()))
+        .sum()
+}
+
+/// Cheapest single exchange between `target` and another rucksack of the group
+/// that makes the whole group repackable without changing its badge.
+fn plan_exchange(group: &[String], target: usize) -> Option<(usize, usize, usize)> {
+    let expected = badge(group);
+    let mut best: Option<(usize, (usize, usize, usize))> = None;
+    for other in (0..group.len()).filter(|o| *o != target) {
+        for (i, a) in group[target].chars().enumerate() {
+            for (j, b) in group[other].chars().enumerate() {
+                if a == b {
+                    continue;
+                }
+                let mut candidate = group.to_vec();
+                exchange(&mut candidate, (target, i), (other, j));
+                if badge(&candidate) != expected {
+                    continue;
+                }
+                if let Some(cost) = total_cost(&candidate) {
+                    if best.is_none_or(|(c, _)| cost < c) {
+                        best = Some((cost, (other, i, j)));
+                    }
+                }
+            }
+        }
+    }
+    best.map(|(_, exchange)| exchange)
+}
+
+/// Repack every rucksack, trading items within its group of three when the
+/// rucksack can't be fixed by swapping between its own compartments alone.
+pub fn repack(input: &str) -> Repacking {
+    let mut repacking = Repacking::default();
+    let lines: Vec<String> = input.lines().map(str::to_string).collect();
+
+    for (g, chunk) in lines.chunks(3).enumerate() {
+        let offset = g * 3;
+        let mut group = chunk.to_vec();
+
+        for target in 0..group.len() {
+            if plan_compartments(&group[target]).is_some() {
+                continue;
+            }
+            if let Some((other, i, j)) = plan_exchange(&group, target) {
+                let items = exchange(&mut group, (target, i), (other, j));
+                repacking.swaps.push(Swap::Rucksacks {
+                    first: (offset + target, i),
+                    second: (offset + other, j),
+                    items,
+                });
+            }
+        }
+
+        for (k, rucksack) in group.into_iter().enumerate() {
+            match plan_compartments(&rucksack) {
+                Some(pairs) => {
+                    let items: Vec<char> = rucksack.chars().collect();
+                    for (left, right) in pairs.iter() {
+                        repacking.swaps.push(Swap::Compartments {
+                            rucksack: offset + k,
+                            left: *left,
+                            right: *right,
+                            items: (items[*left], items[*right]),
+                        });
+                    }
+                    repacking.rucksacks.push(apply(&rucksack, &pairs));
+                }
+                None => {
+                    repacking.unresolved.push(offset + k);
+                    repacking.rucksacks.push(rucksack);
+                }
+            }
+        }
+    }
+    repacking
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repack::*;
+    use std::fs;
+
+    #[test]
+    fn minimal_swaps_test() {
+        assert_eq!(Some(vec![]), plan_compartments("abcd"));
+        assert_eq!(
+            1,
+            plan_compartments("vJrwpWtwJgWrhcsFMMfFFhFp").unwrap().len()
+        );
+        assert_eq!(1, plan_compartments("abab").unwrap().len());
+        assert_eq!(None, plan_compartments("aaab"));
+    }
+
+    #[test]
+    fn repack_test() {
+        let input = fs::read_to_string("test.txt").unwrap();
+        let repacking = repack(&input);
+        assert!(repacking.unresolved.is_empty());
+        assert!(repacking.verify());
+        assert_eq!(9, repacking.swaps.len());
+    }
+
+    #[test]
+    fn group_exchange_test() {
+        let repacking = repack("aaab\nxyzb\nbcde");
+        assert!(repacking.unresolved.is_empty());
+        assert!(repacking.verify());
+        assert!(matches!(repacking.swaps[0], Swap::Rucksacks { .. }));
+    }
+}
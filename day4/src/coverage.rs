@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::interval::IntervalSet;
+
+/// Widest span that still gets an ASCII coverage strip
+const MAX_STRIP_WIDTH: usize = 120;
+
+#[derive(Debug, Default)]
+pub struct Coverage {
+    /// Sections between the lowest and the highest assigned section
+    pub span: Option<RangeInclusive<i32>>,
+    pub uncovered: IntervalSet,
+    pub single: IntervalSet,
+    pub max_depth: usize,
+    pub deepest: IntervalSet,
+    /// Consecutive runs of sections sharing the same depth
+    segments: Vec<(RangeInclusive<i32>, usize)>,
+}
+
+impl Coverage {
+    /// Sweep over the range boundaries, keeping a running count of elves
+    pub fn new<I: IntoIterator<Item = RangeInclusive<i32>>>(ranges: I) -> Self {
+        let mut events: BTreeMap<i64, i64> = BTreeMap::new();
+        for r in ranges.into_iter().filter(|r| !r.is_empty()) {
+            *events.entry(*r.start() as i64).or_insert(0) += 1;
+            *events.entry(*r.end() as i64 + 1).or_insert(0) -= 1;
+        }
+
+        let mut coverage = Coverage::default();
+        let mut depth: i64 = 0;
+        let mut boundaries = events.iter().peekable();
+        while let Some((position, delta)) = boundaries.next() {
+            depth += delta;
+            let Some((next, _)) = boundaries.peek() else {
+                break;
+            };
+            let segment = *position as i32..=(**next - 1) as i32;
+            coverage.segments.push((segment, depth as usize));
+        }
+        if let (Some(first), Some(last)) = (coverage.segments.first(), coverage.segments.last()) {
+            coverage.span = Some(*first.0.start()..=*last.0.end());
+        }
+
+        coverage.max_depth = coverage.segments.iter().map(|(_, d)| *d).max().unwrap_or(0);
+        for (segment, depth) in coverage.segments.iter() {
+            match *depth {
+                0 => coverage.uncovered.insert(segment.clone()),
+                1 => coverage.single.insert(segment.clone()),
+                _ => (),
+            }
+            if *depth == coverage.max_depth && *depth > 0 {
+                coverage.deepest.insert(segment.clone());
+            }
+        }
+        coverage
+    }
+
+    /// One character per section: `.` when uncovered, the depth up to 9 and `+` above
+    pub fn strip(&self) -> Option<String> {
+        let span = self.span.as_ref()?;
+        if (*span.end() as i64 - *span.start() as i64 + 1) as usize > MAX_STRIP_WIDTH {
+            return None;
+        }
+        let mut strip = String::new();
+        for (segment, depth) in self.segments.iter() {
+            let c = match depth {
+                0 => '.',
+                1..=9 => char::from_digit(*depth as u32, 10).unwrap(),
+                _ => '+',
+            };
+            segment.clone().for_each(|_| strip.push(c));
+        }
+        Some(strip)
+    }
+}
+
+fn format_ranges(set: &IntervalSet) -> String {
+    if set.is_empty() {
+        return String::from("none");
+    }
+    set.ranges()
+        .iter()
+        .map(|r| format!("{}-{}", r.start(), r.end()))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(span) = &self.span else {
+            return writeln!(f, "No assignments");
+        };
+        writeln!(f, "Span: {}-{}", span.start(), span.end())?;
+        writeln!(f, "Uncovered: {}", format_ranges(&self.uncovered))?;
+        writeln!(f, "Covered once: {}", format_ranges(&self.single))?;
+        writeln!(
+            f,
+            "Maximum depth {}: {}",
+            self.max_depth,
+            format_ranges(&self.deepest)
+        )?;
+        if let Some(strip) = self.strip() {
+            writeln!(f, "{}", strip)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coverage::*;
+    #[test]
+    fn coverage_test() {
+        let coverage = Coverage::new(vec![1..=3, 2..=4, 8..=9, 3..=3]);
+        assert_eq!(Some(1..=9), coverage.span);
+        assert_eq!(&[5..=7], coverage.uncovered.ranges());
+        assert_eq!(&[1..=1, 4..=4, 8..=9], coverage.single.ranges());
+        assert_eq!(3, coverage.max_depth);
+        assert_eq!(&[3..=3], coverage.deepest.ranges());
+        assert_eq!(Some(String::from("1231...11")), coverage.strip());
+    }
+
+    #[test]
+    fn wide_span_test() {
+        let coverage = Coverage::new(vec![0..=1000]);
+        assert_eq!(None, coverage.strip());
+        assert_eq!(1001, coverage.single.len());
+    }
+}
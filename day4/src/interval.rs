@@ -0,0 +1,161 @@
+use std::ops::RangeInclusive;
+
+/// Set of sections stored as sorted, disjoint and non-adjacent ranges
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    ranges: Vec<RangeInclusive<i32>>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        IntervalSet { ranges: vec![] }
+    }
+
+    pub fn ranges(&self) -> &[RangeInclusive<i32>] {
+        &self.ranges
+    }
+
+    /// Add a range, merging it with any overlapping or adjacent ones
+    pub fn insert(&mut self, range: RangeInclusive<i32>) {
+        if range.is_empty() {
+            return;
+        }
+        let (mut start, mut end) = range.into_inner();
+        let mut merged: Vec<RangeInclusive<i32>> = Vec::with_capacity(self.ranges.len() + 1);
+        let mut placed = false;
+        for r in self.ranges.drain(..) {
+            if (*r.end() as i64) + 1 < start as i64 {
+                merged.push(r);
+            } else if (end as i64) + 1 < *r.start() as i64 {
+                if !placed {
+                    merged.push(start..=end);
+                    placed = true;
+                }
+                merged.push(r);
+            } else {
+                start = start.min(*r.start());
+                end = end.max(*r.end());
+            }
+        }
+        if !placed {
+            merged.push(start..=end);
+        }
+        self.ranges = merged;
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        let mut set = self.clone();
+        other.ranges.iter().for_each(|r| set.insert(r.clone()));
+        set
+    }
+
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges: Vec<RangeInclusive<i32>> = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let start = *a.start().max(b.start());
+            let end = *a.end().min(b.end());
+            if start <= end {
+                ranges.push(start..=end);
+            }
+            if a.end() < b.end() {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    pub fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges: Vec<RangeInclusive<i32>> = Vec::new();
+        for r in self.ranges.iter() {
+            let mut start = *r.start() as i64;
+            let end = *r.end() as i64;
+            for o in other.ranges.iter() {
+                let (o_start, o_end) = (*o.start() as i64, *o.end() as i64);
+                if o_end < start || o_start > end {
+                    continue;
+                }
+                if o_start > start {
+                    ranges.push(start as i32..=(o_start - 1) as i32);
+                }
+                start = o_end + 1;
+            }
+            if start <= end {
+                ranges.push(start as i32..=end as i32);
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    /// Total number of sections covered
+    pub fn len(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|r| (*r.end() as i64 - *r.start() as i64 + 1) as usize)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    #[cfg(test)]
+    pub fn contains(&self, section: i32) -> bool {
+        self.ranges.iter().any(|r| r.contains(&section))
+    }
+
+    pub fn is_superset(&self, other: &IntervalSet) -> bool {
+        other.difference(self).is_empty()
+    }
+
+    pub fn overlaps(&self, other: &IntervalSet) -> bool {
+        !self.intersection(other).is_empty()
+    }
+}
+
+impl From<RangeInclusive<i32>> for IntervalSet {
+    fn from(range: RangeInclusive<i32>) -> Self {
+        let mut set = IntervalSet::new();
+        set.insert(range);
+        set
+    }
+}
+
+impl FromIterator<RangeInclusive<i32>> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<i32>>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        iter.into_iter().for_each(|r| set.insert(r));
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interval::*;
+    #[test]
+    fn merge_test() {
+        let set: IntervalSet = vec![5..=7, 1..=2, 3..=3, 10..=12, 11..=20]
+            .into_iter()
+            .collect();
+        assert_eq!(&[1..=3, 5..=7, 10..=20], set.ranges());
+        assert_eq!(17, set.len());
+        assert!(set.contains(15));
+        assert!(!set.contains(8));
+    }
+
+    #[test]
+    fn set_operations_test() {
+        let a: IntervalSet = vec![1..=10, 20..=30].into_iter().collect();
+        let b: IntervalSet = vec![5..=25].into_iter().collect();
+        assert_eq!(&[1..=30], a.union(&b).ranges());
+        assert_eq!(&[5..=10, 20..=25], a.intersection(&b).ranges());
+        assert_eq!(&[1..=4, 26..=30], a.difference(&b).ranges());
+        assert_eq!(&[11..=19], b.difference(&a).ranges());
+        assert!(a.union(&b).is_superset(&b));
+        assert!(!a.is_superset(&b));
+        assert!(a.overlaps(&b));
+    }
+}
@@ -1,64 +1,347 @@
-use std::{fs, ops::RangeInclusive};
+use std::{env, fmt, fs, ops::RangeInclusive, process};
+
+use coverage::Coverage;
+use interval::IntervalSet;
+
+mod coverage;
+mod interval;
 
 #[derive(Debug)]
-struct AssignmentPairs {
-    first: RangeInclusive<i32>,
-    second: RangeInclusive<i32>,
+struct AssignmentGroup {
+    ranges: Vec<RangeInclusive<i32>>,
 }
 
-impl AssignmentPairs {
-    fn new(first: Vec<i32>, second: Vec<i32>) -> Self {
-        AssignmentPairs {
-            first: (first[0]..=first[1]),
-            second: (second[0]..=second[1]),
-        }
+impl AssignmentGroup {
+    fn new(ranges: Vec<RangeInclusive<i32>>) -> Self {
+        AssignmentGroup { ranges }
     }
 
+    fn sets(&self) -> Vec<IntervalSet> {
+        self.ranges.iter().cloned().map(IntervalSet::from).collect()
+    }
+
+    /// Some range contains all the others, groups with a single elf never overlap
     fn check_full_overlap(&self) -> bool {
-        (self.first.contains(&self.second.start()) && self.first.contains(&self.second.end()))
-            || (self.second.contains(&self.first.start())
-                && self.second.contains(&self.first.end()))
+        if self.ranges.len() < 2 {
+            return false;
+        }
+        let sets = self.sets();
+        sets.iter()
+            .any(|outer| sets.iter().all(|inner| outer.is_superset(inner)))
     }
+
+    /// Some two ranges share at least one section
     fn check_partial_overlap(&self) -> bool {
-        self.first.contains(&self.second.start()) || self.second.contains(&self.first.start())
+        let sets = self.sets();
+        (0..sets.len()).any(|i| (i + 1..sets.len()).any(|j| sets[i].overlaps(&sets[j])))
+    }
+
+    /// `matrix[i][j]` tells whether the i-th and the j-th ranges overlap
+    fn overlap_matrix(&self) -> Vec<Vec<bool>> {
+        self.overlap_lengths()
+            .iter()
+            .map(|row| row.iter().map(|l| *l > 0).collect())
+            .collect()
+    }
+
+    /// `lengths[i][j]` is the number of sections shared by the i-th and the j-th ranges
+    fn overlap_lengths(&self) -> Vec<Vec<usize>> {
+        let sets = self.sets();
+        sets.iter()
+            .map(|a| sets.iter().map(|b| a.intersection(b).len()).collect())
+            .collect()
+    }
+}
+
+impl fmt::Display for AssignmentGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|r| format!("{}-{}", r.start(), r.end()))
+            .collect::<Vec<String>>()
+            .join(",");
+        write!(f, "{}", ranges)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Reject reversed ranges
+    Strict,
+    /// Swap the bounds of reversed ranges
+    Normalise,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ParseError {
+    Reversed { line: usize, field: String },
+    MissingBound { line: usize, field: String },
+    ExtraField { line: usize, field: String },
+    InvalidBound { line: usize, field: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Reversed { line, field } => {
+                write!(f, "line {}: reversed range '{}'", line, field)
+            }
+            ParseError::MissingBound { line, field } => {
+                write!(f, "line {}: missing bound in '{}'", line, field)
+            }
+            ParseError::ExtraField { line, field } => {
+                write!(f, "line {}: extra field in '{}'", line, field)
+            }
+            ParseError::InvalidBound { line, field } => {
+                write!(f, "line {}: invalid bound in '{}'", line, field)
+            }
+        }
     }
 }
 
-fn initialize_pairs(input: &str) -> Vec<AssignmentPairs>{
-    let mut assignment_pairs: Vec<AssignmentPairs> = Vec::new();
-    for line in input.lines() {
-        let [first, second]: [Vec<i32>; 2] = line
-            .split(",")
-            .map(|pair| {
-                pair.split("-")
-                    .map(|n| n.parse::<i32>().unwrap_or_default())
-                    .collect::<Vec<i32>>()
+/// Reversed range rewritten in normalising mode
+#[derive(Debug, PartialEq, Eq)]
+struct Fix {
+    line: usize,
+    field: String,
+    range: RangeInclusive<i32>,
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: '{}' normalised to {}-{}",
+            self.line,
+            self.field,
+            self.range.start(),
+            self.range.end()
+        )
+    }
+}
+
+fn parse_range(
+    field: &str,
+    line: usize,
+    mode: Mode,
+) -> Result<(RangeInclusive<i32>, Option<Fix>), ParseError> {
+    let error_field = field.to_string();
+    let bounds: Vec<&str> = field.trim().split('-').collect();
+    let [start, end]: [&str; 2] = match bounds.len() {
+        2 => [bounds[0].trim(), bounds[1].trim()],
+        0 | 1 => {
+            return Err(ParseError::MissingBound {
+                line,
+                field: error_field,
             })
-            .collect::<Vec<Vec<i32>>>()
-            .try_into()
-            .unwrap();
-        assignment_pairs.push(AssignmentPairs::new(first, second));
+        }
+        _ => {
+            return Err(ParseError::ExtraField {
+                line,
+                field: error_field,
+            })
+        }
+    };
+    if start.is_empty() || end.is_empty() {
+        return Err(ParseError::MissingBound {
+            line,
+            field: error_field,
+        });
+    }
+    let (Ok(start), Ok(end)) = (start.parse::<i32>(), end.parse::<i32>()) else {
+        return Err(ParseError::InvalidBound {
+            line,
+            field: error_field,
+        });
+    };
+    if start <= end {
+        return Ok((start..=end, None));
+    }
+    match mode {
+        Mode::Strict => Err(ParseError::Reversed {
+            line,
+            field: error_field,
+        }),
+        Mode::Normalise => Ok((
+            end..=start,
+            Some(Fix {
+                line,
+                field: error_field,
+                range: end..=start,
+            }),
+        )),
+    }
+}
+
+/// Parse every line, collecting all the errors instead of stopping at the first one.
+/// Blank lines are skipped, line numbers start at 1.
+fn initialize_groups(
+    input: &str,
+    mode: Mode,
+) -> Result<(Vec<AssignmentGroup>, Vec<Fix>), Vec<ParseError>> {
+    let mut assignment_groups: Vec<AssignmentGroup> = Vec::new();
+    let mut fixes: Vec<Fix> = Vec::new();
+    let mut errors: Vec<ParseError> = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut ranges: Vec<RangeInclusive<i32>> = Vec::new();
+        for field in line.split(',') {
+            match parse_range(field, i + 1, mode) {
+                Ok((range, fix)) => {
+                    ranges.push(range);
+                    fixes.extend(fix);
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+        assignment_groups.push(AssignmentGroup::new(ranges));
+    }
+    if errors.is_empty() {
+        Ok((assignment_groups, fixes))
+    } else {
+        Err(errors)
     }
-    assignment_pairs
 }
 
-fn part1(assignment_pairs: &Vec<AssignmentPairs>) -> i32 {
-    assignment_pairs
+fn part1(assignment_groups: &[AssignmentGroup]) -> i32 {
+    assignment_groups
         .iter()
-        .fold(0, |sum, pair| sum + pair.check_full_overlap() as i32)
+        .fold(0, |sum, group| sum + group.check_full_overlap() as i32)
 }
 
-fn part2(assignment_pairs: &Vec<AssignmentPairs>) -> i32 {
-    assignment_pairs
+fn part2(assignment_groups: &[AssignmentGroup]) -> i32 {
+    assignment_groups
         .iter()
-        .fold(0, |sum, pair| sum + pair.check_partial_overlap() as i32)
+        .fold(0, |sum, group| sum + group.check_partial_overlap() as i32)
+}
+
+/// Section coverage over every elf of every group
+fn coverage(assignment_groups: &[AssignmentGroup]) -> Coverage {
+    Coverage::new(
+        assignment_groups
+            .iter()
+            .flat_map(|group| group.ranges.iter().cloned()),
+    )
+}
+
+fn print_groups(assignment_groups: &[AssignmentGroup]) {
+    for (i, group) in assignment_groups.iter().enumerate() {
+        println!(
+            "Line {}: {} contains all: {} any overlap: {}",
+            i + 1,
+            group,
+            group.check_full_overlap(),
+            group.check_partial_overlap()
+        );
+        for row in group.overlap_matrix() {
+            let row: String = row.iter().map(|o| if *o { '#' } else { '.' }).collect();
+            println!("  {}", row);
+        }
+    }
+}
+
+fn print_overlaps(assignment_groups: &[AssignmentGroup]) {
+    for (line, group) in assignment_groups.iter().enumerate() {
+        for (i, row) in group.overlap_lengths().iter().enumerate() {
+            for (j, length) in row.iter().enumerate().skip(i + 1) {
+                println!(
+                    "Line {}: {}-{} and {}-{} overlap by {}",
+                    line + 1,
+                    group.ranges[i].start(),
+                    group.ranges[i].end(),
+                    group.ranges[j].start(),
+                    group.ranges[j].end(),
+                    length
+                );
+            }
+        }
+    }
+}
+
+/// Sections left to each elf of a line once pruned
+#[derive(Debug, PartialEq, Eq)]
+struct PrunedGroup {
+    sets: Vec<IntervalSet>,
+}
+
+impl fmt::Display for PrunedGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = self
+            .sets
+            .iter()
+            .map(|set| match set.is_empty() {
+                true => String::from("-"),
+                false => set
+                    .ranges()
+                    .iter()
+                    .map(|r| format!("{}-{}", r.start(), r.end()))
+                    .collect::<Vec<String>>()
+                    .join("+"),
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        write!(f, "{}", fields)
+    }
+}
+
+/// Rewrite the assignments so that no section is cleaned twice.
+/// Earlier ranges win: later ones keep only their uncovered sections. Every
+/// line and every range keep their place, so a range cut in the middle is
+/// written as its pieces joined with `+` (`1-4+6-9`) and a range with
+/// nothing left as `-`.
+fn prune(assignment_groups: &[AssignmentGroup]) -> Vec<PrunedGroup> {
+    let mut covered = IntervalSet::new();
+    let mut pruned: Vec<PrunedGroup> = Vec::new();
+    for group in assignment_groups.iter() {
+        let mut sets: Vec<IntervalSet> = Vec::new();
+        for set in group.sets() {
+            let remaining = set.difference(&covered);
+            covered = covered.union(&remaining);
+            sets.push(remaining);
+        }
+        pruned.push(PrunedGroup { sets });
+    }
+    pruned
 }
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mode = if args.iter().any(|a| a == "--normalise") {
+        Mode::Normalise
+    } else {
+        Mode::Strict
+    };
+
     let input = fs::read_to_string("input.txt").unwrap();
-    let assignment_pairs: Vec<AssignmentPairs> = initialize_pairs(&input);
-    println!("Part 1: {}", part1(&assignment_pairs));
-    println!("Part 2: {}", part2(&assignment_pairs));
+    let assignment_groups: Vec<AssignmentGroup> = match initialize_groups(&input, mode) {
+        Ok((assignment_groups, fixes)) => {
+            fixes.iter().for_each(|fix| eprintln!("{}", fix));
+            assignment_groups
+        }
+        Err(errors) => {
+            errors.iter().for_each(|error| eprintln!("{}", error));
+            process::exit(1);
+        }
+    };
+    match args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .map(String::as_str)
+    {
+        Some("coverage") => print!("{}", coverage(&assignment_groups)),
+        Some("groups") => print_groups(&assignment_groups),
+        Some("overlaps") => print_overlaps(&assignment_groups),
+        Some("prune") => prune(&assignment_groups)
+            .iter()
+            .for_each(|group| println!("{}", group)),
+        _ => {
+            println!("Part 1: {}", part1(&assignment_groups));
+            println!("Part 2: {}", part2(&assignment_groups));
+        }
+    }
 }
 
 #[cfg(test)]
@@ -67,14 +350,133 @@ mod tests {
     #[test]
     fn part1_test() {
         let input = fs::read_to_string("test.txt").unwrap();
-        let assignment_pairs = initialize_pairs(&input);
-        assert_eq!(2, part1(&assignment_pairs));
+        let assignment_groups = initialize_groups(&input, Mode::Strict).unwrap().0;
+        assert_eq!(2, part1(&assignment_groups));
     }
 
     #[test]
     fn part2_test() {
         let input = fs::read_to_string("test.txt").unwrap();
-        let assignment_pairs = initialize_pairs(&input);
-        assert_eq!(4, part2(&assignment_pairs));
+        let assignment_groups = initialize_groups(&input, Mode::Strict).unwrap().0;
+        assert_eq!(4, part2(&assignment_groups));
+    }
+
+    #[test]
+    fn coverage_test() {
+        let input = fs::read_to_string("test.txt").unwrap();
+        let assignment_groups = initialize_groups(&input, Mode::Strict).unwrap().0;
+        let coverage = coverage(&assignment_groups);
+        assert!(coverage.uncovered.is_empty());
+        assert_eq!(&[9..=9], coverage.single.ranges());
+        assert_eq!(8, coverage.max_depth);
+        assert_eq!(&[6..=6], coverage.deepest.ranges());
+        assert_eq!(Some(String::from("45778641")), coverage.strip());
+    }
+
+    #[test]
+    fn groups_test() {
+        let assignment_groups =
+            initialize_groups("1-9,2-3,4-8\n1-2,3-4,5-6,6-7\n1-3,4-5,6-9", Mode::Strict)
+                .unwrap()
+                .0;
+        let full: Vec<bool> = assignment_groups
+            .iter()
+            .map(|g| g.check_full_overlap())
+            .collect();
+        let partial: Vec<bool> = assignment_groups
+            .iter()
+            .map(|g| g.check_partial_overlap())
+            .collect();
+        assert_eq!(vec![true, false, false], full);
+        assert_eq!(vec![true, true, false], partial);
+        assert_eq!(
+            vec![
+                vec![true, false, false, false],
+                vec![false, true, false, false],
+                vec![false, false, true, true],
+                vec![false, false, true, true],
+            ],
+            assignment_groups[1].overlap_matrix()
+        );
+    }
+
+    #[test]
+    fn overlap_lengths_test() {
+        let input = fs::read_to_string("test.txt").unwrap();
+        let assignment_groups = initialize_groups(&input, Mode::Strict).unwrap().0;
+        let lengths: Vec<usize> = assignment_groups
+            .iter()
+            .map(|g| g.overlap_lengths()[0][1])
+            .collect();
+        assert_eq!(vec![0, 0, 1, 5, 1, 3], lengths);
+    }
+
+    #[test]
+    fn prune_test() {
+        let input = fs::read_to_string("test.txt").unwrap();
+        let pruned = prune(&initialize_groups(&input, Mode::Strict).unwrap().0);
+        let output: Vec<String> = pruned.iter().map(|g| g.to_string()).collect();
+        assert_eq!(
+            vec!["2-4,6-8", "-,5-5", "-,9-9", "-,-", "-,-", "-,-"],
+            output
+        );
+        let ranges = pruned
+            .iter()
+            .flat_map(|g| g.sets.iter().flat_map(|s| s.ranges().to_vec()));
+        assert_eq!(1, Coverage::new(ranges).max_depth);
+
+        let pruned = prune(&initialize_groups("1-9\n4-5,3-12", Mode::Strict).unwrap().0);
+        let output: Vec<String> = pruned.iter().map(|g| g.to_string()).collect();
+        assert_eq!(vec!["1-9", "-,10-12"], output);
+
+        let pruned = prune(&initialize_groups("5-5\n1-9,2-3", Mode::Strict).unwrap().0);
+        let output: Vec<String> = pruned.iter().map(|g| g.to_string()).collect();
+        assert_eq!(vec!["5-5", "1-4+6-9,-"], output);
+    }
+
+    #[test]
+    fn validation_test() {
+        let errors =
+            initialize_groups("7-3,2-5\n1-,4\n1-2-3,5-6\n1-2,x-4\n1-2,", Mode::Strict).unwrap_err();
+        assert_eq!(
+            vec![
+                ParseError::Reversed {
+                    line: 1,
+                    field: String::from("7-3")
+                },
+                ParseError::MissingBound {
+                    line: 2,
+                    field: String::from("1-")
+                },
+                ParseError::MissingBound {
+                    line: 2,
+                    field: String::from("4")
+                },
+                ParseError::ExtraField {
+                    line: 3,
+                    field: String::from("1-2-3")
+                },
+                ParseError::InvalidBound {
+                    line: 4,
+                    field: String::from("x-4")
+                },
+                ParseError::MissingBound {
+                    line: 5,
+                    field: String::from("")
+                },
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn normalise_test() {
+        let (assignment_groups, fixes) =
+            initialize_groups("7-3,2-5\n\n2-4,9-6", Mode::Normalise).unwrap();
+        assert_eq!(vec![3..=7, 2..=5], assignment_groups[0].ranges);
+        assert_eq!(vec![2..=4, 6..=9], assignment_groups[1].ranges);
+        assert_eq!(2, fixes.len());
+        assert_eq!("line 3: '9-6' normalised to 6-9", fixes[1].to_string());
+        assert!(initialize_groups("1-2,x-4", Mode::Normalise).is_err());
     }
 }
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::crane::Crane;
+use crate::stepper::Stepper;
+use crate::{render, Crate, Instruction, InstructionError};
+
+const CRATE_WIDTH: usize = 36;
+const CRATE_HEIGHT: usize = 28;
+const GAP: usize = 10;
+const MARGIN: usize = 20;
+const SECONDS_PER_MOVE: f64 = 0.6;
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    Instruction(InstructionError),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(error) => write!(f, "{}", error),
+            ExportError::Instruction(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(error: io::Error) -> Self {
+        ExportError::Io(error)
+    }
+}
+
+impl From<InstructionError> for ExportError {
+    fn from(error: InstructionError) -> Self {
+        ExportError::Instruction(error)
+    }
+}
+
+/// Starting stacks followed by the stacks after every instruction
+pub fn frames(
+    crates: Vec<Crate>,
+    instructions: &[Instruction],
+    crane: &dyn Crane,
+) -> Result<Vec<Vec<Crate>>, InstructionError> {
+    let mut frames = vec![crates.clone()];
+    for state in Stepper::new(crates, instructions, crane) {
+        frames.push(state?);
+    }
+    Ok(frames)
+}
+
+/// Follow every crate from frame to frame, returning its letter and its
+/// `(stack, level)` in each frame. Crates below the first change of a stack
+/// stay where they are, the others are matched by letter, which can only
+/// swap crates that look the same.
+fn track(frames: &[Vec<Crate>]) -> Vec<(char, Vec<(usize, usize)>)> {
+    let mut crates: Vec<(char, Vec<(usize, usize)>)> = Vec::new();
+    let mut ids: Vec<Vec<usize>> = Vec::new();
+    for (s, stack) in frames[0].iter().enumerate() {
+        ids.push(Vec::new());
+        for (level, c) in stack.iter().enumerate() {
+            ids[s].push(crates.len());
+            crates.push((*c, vec![(s, level)]));
+        }
+    }
+
+    for (before, after) in frames.iter().zip(frames.iter().skip(1)) {
+        let unchanged: Vec<usize> = before
+            .iter()
+            .zip(after.iter())
+            .map(|(b, a)| b.iter().zip(a.iter()).take_while(|(x, y)| x == y).count())
+            .collect();
+        let mut lifted: HashMap<char, Vec<usize>> = HashMap::new();
+        for (s, stack) in ids.iter_mut().enumerate() {
+            for id in stack.drain(unchanged[s]..) {
+                lifted.entry(crates[id].0).or_default().push(id);
+            }
+        }
+        for (s, stack) in after.iter().enumerate() {
+            for c in stack.iter().skip(unchanged[s]) {
+                let id = lifted.get_mut(c).and_then(|ids| ids.pop()).unwrap();
+                ids[s].push(id);
+            }
+        }
+        for (s, stack) in ids.iter().enumerate() {
+            for (level, id) in stack.iter().enumerate() {
+                crates[*id].1.push((s, level));
+            }
+        }
+    }
+    crates
+}
+
+fn x(stack: usize) -> usize {
+    MARGIN + stack * (CRATE_WIDTH + GAP)
+}
+
+/// Leaves room at the top for the current instruction and the lifted crates
+fn y(level: usize, height: usize) -> usize {
+    3 * MARGIN + CRATE_HEIGHT * (height + 1) - CRATE_HEIGHT * (level + 1)
+}
+
+/// Animated SVG where each move lifts the crates above the stacks, carries
+/// them across and puts them down, one move every `SECONDS_PER_MOVE`
+pub fn svg(frames: &[Vec<Crate>], instructions: &[Instruction]) -> String {
+    let stacks = frames[0].len();
+    let height = frames
+        .iter()
+        .flat_map(|f| f.iter().map(|s| s.len()))
+        .max()
+        .unwrap_or(0);
+    let width = 2 * MARGIN + stacks * (CRATE_WIDTH + GAP);
+    let total_height = y(0, height) + CRATE_HEIGHT + 2 * MARGIN;
+    let moves = frames.len() - 1;
+    let duration = SECONDS_PER_MOVE * moves.max(1) as f64;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="monospace" font-size="16">"#,
+        width, total_height
+    )
+    .unwrap();
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    for s in 0..stacks {
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+            x(s) + CRATE_WIDTH / 2,
+            y(0, height) + CRATE_HEIGHT + MARGIN,
+            s + 1
+        )
+        .unwrap();
+    }
+
+    // Each caption shows up during its own move only, whatever the number of moves
+    for (k, instruction) in instructions.iter().take(moves).enumerate() {
+        let (shown, hidden) = (k as f64 / moves as f64, (k + 1) as f64 / moves as f64);
+        let (values, key_times) = if k == 0 {
+            ("visible;hidden".to_string(), format!("0;{:.4}", hidden))
+        } else {
+            (
+                "hidden;visible;hidden".to_string(),
+                format!("0;{:.4};{:.4}", shown, hidden),
+            )
+        };
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" visibility="hidden">{}<animate attributeName="visibility" values="{}" keyTimes="{}" calcMode="discrete" dur="{}s" repeatCount="indefinite"/></text>"#,
+            MARGIN, MARGIN, instruction, values, key_times, duration
+        )
+        .unwrap();
+    }
+
+    // Crates travel above the highest possible stack
+    let lift = y(height, height) - CRATE_HEIGHT / 2;
+    for (c, positions) in track(frames).iter() {
+        let mut values: Vec<(usize, usize)> = vec![(x(positions[0].0), y(positions[0].1, height))];
+        for pair in positions.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            if from == to {
+                values.extend([(x(from.0), y(from.1, height)); 3]);
+            } else {
+                values.push((x(from.0), lift));
+                values.push((x(to.0), lift));
+                values.push((x(to.0), y(to.1, height)));
+            }
+        }
+        writeln!(
+            svg,
+            r#"<g transform="translate({},{})">"#,
+            values[0].0, values[0].1
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<rect width="{}" height="{}" fill="burlywood" stroke="saddlebrown"/>"#,
+            CRATE_WIDTH,
+            CRATE_HEIGHT - 2
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+            CRATE_WIDTH / 2,
+            CRATE_HEIGHT / 2 + 5,
+            c
+        )
+        .unwrap();
+        if moves > 0 {
+            let translations: Vec<String> =
+                values.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+            let key_times: Vec<String> = (0..values.len())
+                .map(|i| format!("{:.4}", i as f64 / (values.len() - 1) as f64))
+                .collect();
+            writeln!(
+                svg,
+                r#"<animateTransform attributeName="transform" type="translate" values="{}" keyTimes="{}" dur="{}s" repeatCount="indefinite"/>"#,
+                translations.join(";"),
+                key_times.join(";"),
+                duration
+            )
+            .unwrap();
+        }
+        writeln!(svg, "</g>").unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Write `frame_NNNN.txt` for the starting drawing and after every
+/// instruction, plus `animation.svg`, returning the number of frames
+pub fn export(
+    dir: &Path,
+    crates: Vec<Crate>,
+    instructions: &[Instruction],
+    crane: &dyn Crane,
+) -> Result<usize, ExportError> {
+    let frames = frames(crates, instructions, crane)?;
+    fs::create_dir_all(dir)?;
+    let digits = frames.len().to_string().len().max(4);
+    for (i, frame) in frames.iter().enumerate() {
+        let name = format!("frame_{:0digits$}.txt", i, digits = digits);
+        fs::write(dir.join(name), render(frame) + "\n")?;
+    }
+    fs::write(dir.join("animation.svg"), svg(&frames, instructions))?;
+    Ok(frames.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::animation::*;
+    use crate::crane::{CrateMover9000, CrateMover9001};
+    use crate::{initialize_crates, split_input};
+
+    #[test]
+    fn track_test() {
+        let input = fs::read_to_string("test.txt").unwrap();
+        let (crates, instructions) = split_input(&input);
+        for crane in [&CrateMover9000 as &dyn Crane, &CrateMover9001] {
+            let frames = frames(crates.clone(), &instructions, crane).unwrap();
+            assert_eq!(5, frames.len());
+            for (c, positions) in track(&frames).iter() {
+                assert_eq!(frames.len(), positions.len());
+                for (frame, (stack, level)) in frames.iter().zip(positions.iter()) {
+                    assert_eq!(*c, frame[*stack][*level]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn export_test() {
+        let input = fs::read_to_string("test.txt").unwrap();
+        let (crates, instructions) = split_input(&input);
+        let dir = std::env::temp_dir().join(format!("day5_export_{}", std::process::id()));
+        assert_eq!(
+            5,
+            export(&dir, crates, &instructions, &CrateMover9000).unwrap()
+        );
+
+        let last = fs::read_to_string(dir.join("frame_0004.txt")).unwrap();
+        assert_eq!("CMZ", crate::read_top(&initialize_crates(&last)));
+        let svg = fs::read_to_string(dir.join("animation.svg")).unwrap();
+        assert_eq!(6, svg.matches("<animateTransform").count());
+        assert!(svg.contains("move 3 from 1 to 3"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn svg_size_test() {
+        let (crates, instructions) = crate::bench::random_procedure(3, 2, 400, 5);
+        let sizes: Vec<usize> = [100, 400]
+            .iter()
+            .map(|moves| {
+                let frames =
+                    frames(crates.clone(), &instructions[..*moves], &CrateMover9000).unwrap();
+                svg(&frames, &instructions[..*moves]).len()
+            })
+            .collect();
+        // Captions and crate paths grow with the number of moves, not its square
+        assert!(sizes[1] < 5 * sizes[0]);
+        assert!(sizes[1] < 400 * 1_000);
+    }
+}
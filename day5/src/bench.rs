@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+
+use crate::crane::{Crane, CrateMover9000, CrateMover9001};
+use crate::{read_top, Crate, Instruction};
+
+/// Xorshift generator, enough to build reproducible procedures
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+/// Valid procedure of `moves` instructions over `stacks` stacks starting with
+/// `height` crates each
+pub fn random_procedure(
+    stacks: usize,
+    height: usize,
+    moves: usize,
+    seed: u64,
+) -> (Vec<Crate>, Vec<Instruction>) {
+    let mut rng = Rng(seed | 1);
+    let crates: Vec<Crate> = (0..stacks)
+        .map(|_| {
+            (0..height)
+                .map(|_| (b'A' + rng.below(26) as u8) as char)
+                .collect()
+        })
+        .collect();
+    let mut heights: Vec<usize> = vec![height; stacks];
+    let mut instructions: Vec<Instruction> = Vec::with_capacity(moves);
+    for line in 0..moves {
+        let mut from = rng.below(stacks);
+        while heights[from] == 0 {
+            from = (from + 1) % stacks;
+        }
+        let to = (from + 1 + rng.below(stacks - 1)) % stacks;
+        let items = 1 + rng.below(heights[from]);
+        heights[from] -= items;
+        heights[to] += items;
+        instructions.push(Instruction {
+            items,
+            from: from + 1,
+            to: to + 1,
+            line: line + 1,
+        });
+    }
+    (crates, instructions)
+}
+
+/// Crate by crate moves, the way day5 used to do them
+fn one_at_a_time(crates: &mut [Crate], instructions: &[Instruction], keep_order: bool) {
+    for Instruction {
+        items, from, to, ..
+    } in instructions.iter()
+    {
+        let mut tmp_stack: Crate = Crate::new();
+        for _ in 0..*items {
+            tmp_stack.push(crates[from - 1].pop().unwrap());
+        }
+        if !keep_order {
+            tmp_stack.reverse();
+        }
+        while let Some(c) = tmp_stack.pop() {
+            crates[to - 1].push(c);
+        }
+    }
+}
+
+fn time<F: FnOnce()>(f: F) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+/// Compare whole-slice moves with crate by crate moves on a random procedure
+pub fn run(moves: usize) {
+    let (crates, instructions) = random_procedure(9, 1_000, moves, 2022);
+    println!(
+        "{} moves, {} crates in total",
+        moves,
+        instructions.iter().map(|i| i.items).sum::<usize>()
+    );
+    for (name, crane, keep_order) in [
+        ("CrateMover 9000", &CrateMover9000 as &dyn Crane, false),
+        ("CrateMover 9001", &CrateMover9001 as &dyn Crane, true),
+    ] {
+        let mut bulk = crates.clone();
+        let bulk_time = time(|| {
+            for instruction in instructions.iter() {
+                crane.lift(&mut bulk, instruction).unwrap();
+            }
+        });
+        let mut reference = crates.clone();
+        let reference_time = time(|| one_at_a_time(&mut reference, &instructions, keep_order));
+        assert_eq!(reference, bulk);
+        println!(
+            "{}: {:?} moving slices, {:?} one crate at a time, top {}",
+            name,
+            bulk_time,
+            reference_time,
+            read_top(&bulk)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::*;
+    use crate::{rearrange, OnError};
+
+    #[test]
+    fn bulk_moves_test() {
+        let (crates, instructions) = random_procedure(5, 20, 2_000, 7);
+        for (crane, keep_order) in [
+            (&CrateMover9000 as &dyn Crane, false),
+            (&CrateMover9001 as &dyn Crane, true),
+        ] {
+            let mut bulk = crates.clone();
+            assert!(rearrange(&mut bulk, &instructions, crane, OnError::Abort)
+                .unwrap()
+                .is_empty());
+            let mut reference = crates.clone();
+            one_at_a_time(&mut reference, &instructions, keep_order);
+            assert_eq!(reference, bulk);
+        }
+    }
+}
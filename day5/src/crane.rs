@@ -0,0 +1,314 @@
+use std::fmt;
+
+use crate::{Crate, Instruction};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CraneError {
+    /// The crates don't fit under the maximum stack height
+    Overflow {
+        stack: usize,
+        height: usize,
+        items: usize,
+        max_height: usize,
+    },
+}
+
+impl fmt::Display for CraneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CraneError::Overflow {
+                stack,
+                height,
+                items,
+                max_height,
+            } => write!(
+                f,
+                "stack {} holds {} crates and can't take {} more (maximum height {})",
+                stack, height, items, max_height
+            ),
+        }
+    }
+}
+
+pub trait Crane {
+    /// Carry out a single instruction
+    fn lift(&self, crates: &mut [Crate], instruction: &Instruction) -> Result<(), CraneError>;
+
+    /// Whether the move leaves the stacks as they are
+    fn is_noop(&self, instruction: &Instruction) -> bool {
+        instruction.items == 0 || instruction.from == instruction.to
+    }
+
+    /// Whether `second` puts back exactly what `first` moved. Without knowing
+    /// more about the crane, only single crates surely go back the way they came.
+    fn cancels(&self, first: &Instruction, second: &Instruction) -> bool {
+        first.items == 1 && reverses(first, second)
+    }
+
+    /// Single instruction with the same effect as `first` followed by `second`,
+    /// by default only for a single crate carried on through another stack
+    fn merge(&self, first: &Instruction, second: &Instruction) -> Option<Instruction> {
+        chain(first, second).filter(|_| first.items == 1)
+    }
+}
+
+/// `second` moves as many crates as `first` back to where they came from
+fn reverses(first: &Instruction, second: &Instruction) -> bool {
+    first.items == second.items && first.from == second.to && first.to == second.from
+}
+
+/// Direct move for `first` followed by `second` carrying the same crates on
+fn chain(first: &Instruction, second: &Instruction) -> Option<Instruction> {
+    (first.items == second.items && first.to == second.from && second.to != first.from).then_some(
+        Instruction {
+            to: second.to,
+            ..*first
+        },
+    )
+}
+
+/// Moves the crates one at a time
+pub struct CrateMover9000;
+
+/// Moves the crates all at once, keeping their order
+pub struct CrateMover9001;
+
+/// Moves at most `capacity` crates per lift, keeping their order within a lift
+pub struct BatchCrane {
+    pub capacity: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Refuse moves that would exceed the maximum height
+    Reject,
+    /// Carry the excess crates one at a time to the next stacks with room
+    Spill,
+}
+
+/// Any crane working on stacks that can't grow past `max_height`
+pub struct HeightLimit {
+    pub crane: Box<dyn Crane>,
+    pub max_height: usize,
+    pub overflow: Overflow,
+}
+
+/// Source and destination stacks of a move between two different stacks
+fn source_and_destination(
+    crates: &mut [Crate],
+    from: usize,
+    to: usize,
+) -> (&mut Crate, &mut Crate) {
+    if from < to {
+        let (left, right) = crates.split_at_mut(to);
+        (&mut left[from], &mut right[0])
+    } else {
+        let (left, right) = crates.split_at_mut(from);
+        (&mut right[0], &mut left[to])
+    }
+}
+
+impl Crane for CrateMover9000 {
+    fn lift(
+        &self,
+        crates: &mut [Crate],
+        Instruction {
+            items, from, to, ..
+        }: &Instruction,
+    ) -> Result<(), CraneError> {
+        if from != to {
+            let (source, destination) = source_and_destination(crates, from - 1, to - 1);
+            let at = source.len() - items;
+            destination.extend(source.drain(at..).rev());
+        }
+        Ok(())
+    }
+
+    fn cancels(&self, first: &Instruction, second: &Instruction) -> bool {
+        reverses(first, second)
+    }
+
+    /// Crates go one at a time, so consecutive moves between the same stacks add up
+    fn merge(&self, first: &Instruction, second: &Instruction) -> Option<Instruction> {
+        if (first.from, first.to) == (second.from, second.to) {
+            return Some(Instruction {
+                items: first.items + second.items,
+                ..*first
+            });
+        }
+        chain(first, second).filter(|_| first.items == 1)
+    }
+}
+
+impl Crane for CrateMover9001 {
+    fn lift(
+        &self,
+        crates: &mut [Crate],
+        Instruction {
+            items, from, to, ..
+        }: &Instruction,
+    ) -> Result<(), CraneError> {
+        if from != to {
+            let (source, destination) = source_and_destination(crates, from - 1, to - 1);
+            let at = source.len() - items;
+            destination.extend(source.drain(at..));
+        }
+        Ok(())
+    }
+
+    fn cancels(&self, first: &Instruction, second: &Instruction) -> bool {
+        reverses(first, second)
+    }
+
+    fn merge(&self, first: &Instruction, second: &Instruction) -> Option<Instruction> {
+        chain(first, second)
+    }
+}
+
+impl Crane for BatchCrane {
+    fn lift(&self, crates: &mut [Crate], instruction: &Instruction) -> Result<(), CraneError> {
+        let mut remaining = instruction.items;
+        while remaining > 0 {
+            let items = remaining.min(self.capacity);
+            CrateMover9001.lift(
+                crates,
+                &Instruction {
+                    items,
+                    ..*instruction
+                },
+            )?;
+            remaining -= items;
+        }
+        Ok(())
+    }
+
+    /// Moves that fit in one lift behave like the CrateMover 9001
+    fn cancels(&self, first: &Instruction, second: &Instruction) -> bool {
+        first.items <= self.capacity && reverses(first, second)
+    }
+
+    fn merge(&self, first: &Instruction, second: &Instruction) -> Option<Instruction> {
+        chain(first, second).filter(|_| first.items <= self.capacity)
+    }
+}
+
+impl Crane for HeightLimit {
+    fn lift(&self, crates: &mut [Crate], instruction: &Instruction) -> Result<(), CraneError> {
+        let Instruction {
+            items, from, to, ..
+        } = *instruction;
+        let height = crates[to - 1].len();
+        let incoming = if from == to { 0 } else { items };
+        let excess = (height + incoming).saturating_sub(self.max_height);
+        let room: usize = crates
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != to - 1)
+            .map(|(i, s)| {
+                let height = if i == from - 1 {
+                    s.len().saturating_sub(items)
+                } else {
+                    s.len()
+                };
+                self.max_height.saturating_sub(height)
+            })
+            .sum();
+        if excess > 0 && (self.overflow == Overflow::Reject || excess > room) {
+            return Err(CraneError::Overflow {
+                stack: to,
+                height,
+                items,
+                max_height: self.max_height,
+            });
+        }
+
+        self.crane.lift(crates, instruction)?;
+        let mut next = to % crates.len();
+        for _ in 0..excess {
+            while next == to - 1 || crates[next].len() >= self.max_height {
+                next = (next + 1) % crates.len();
+            }
+            let c = crates[to - 1].pop().unwrap();
+            crates[next].push(c);
+        }
+        Ok(())
+    }
+
+    /// Even a move onto its own stack spills the crates above the limit
+    fn is_noop(&self, instruction: &Instruction) -> bool {
+        self.overflow == Overflow::Reject && self.crane.is_noop(instruction)
+    }
+
+    /// Rejected moves never happen in a valid procedure, but spilled crates
+    /// end up on other stacks so nothing can be assumed about them
+    fn cancels(&self, first: &Instruction, second: &Instruction) -> bool {
+        self.overflow == Overflow::Reject && self.crane.cancels(first, second)
+    }
+
+    fn merge(&self, first: &Instruction, second: &Instruction) -> Option<Instruction> {
+        match self.overflow {
+            Overflow::Reject => self.crane.merge(first, second),
+            Overflow::Spill => None,
+        }
+    }
+}
+
+/// Crane model from its CLI name: `9000`, `9001` or `batch:K`
+pub fn crane_by_name(name: &str) -> Option<Box<dyn Crane>> {
+    match name.split_once(':') {
+        None if name == "9000" => Some(Box::new(CrateMover9000)),
+        None if name == "9001" => Some(Box::new(CrateMover9001)),
+        Some(("batch", capacity)) => match capacity.parse::<usize>() {
+            Ok(capacity) if capacity > 0 => Some(Box::new(BatchCrane { capacity })),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crane::*;
+
+    fn stacks(stacks: &[&str]) -> Vec<Crate> {
+        stacks.iter().map(|s| s.chars().collect()).collect()
+    }
+
+    #[test]
+    fn batch_crane_test() {
+        let mut crates = stacks(&["ABCDE", ""]);
+        let instruction = Instruction::new("move 5 from 1 to 2");
+        BatchCrane { capacity: 2 }
+            .lift(&mut crates, &instruction)
+            .unwrap();
+        assert_eq!(stacks(&["", "DEBCA"]), crates);
+    }
+
+    #[test]
+    fn height_limit_test() {
+        let instruction = Instruction::new("move 3 from 1 to 2");
+        let mut crates = stacks(&["ABC", "D", "E"]);
+        let crane = HeightLimit {
+            crane: Box::new(CrateMover9001),
+            max_height: 3,
+            overflow: Overflow::Reject,
+        };
+        assert!(crane.lift(&mut crates, &instruction).is_err());
+        assert_eq!(stacks(&["ABC", "D", "E"]), crates);
+
+        let crane = HeightLimit {
+            overflow: Overflow::Spill,
+            ..crane
+        };
+        crane.lift(&mut crates, &instruction).unwrap();
+        assert_eq!(stacks(&["", "DAB", "EC"]), crates);
+    }
+
+    #[test]
+    fn crane_by_name_test() {
+        assert!(crane_by_name("9000").is_some());
+        assert!(crane_by_name("batch:3").is_some());
+        assert!(crane_by_name("batch:0").is_none());
+        assert!(crane_by_name("9002").is_none());
+    }
+}
@@ -1,121 +1,486 @@
-use std::collections::VecDeque;
-use std::fs;
+use std::path::Path;
+use std::{env, fmt, fs, io, process};
 
+use crane::{
+    crane_by_name, Crane, CraneError, CrateMover9000, CrateMover9001, HeightLimit, Overflow,
+};
+
+mod animation;
+mod bench;
+mod crane;
+mod optimise;
+mod solver;
+mod stepper;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Instruction {
     items: usize,
     from: usize,
     to: usize,
+    /// Line of the input file the instruction was read from
+    line: usize,
 }
 
-type Crate = VecDeque<char>;
+type Crate = Vec<char>;
 
 impl Instruction {
+    /// Read `move N from A to B`, anything else being malformed
+    fn parse(instruction: &str, line: usize) -> Result<Self, InstructionError> {
+        let malformed = || InstructionError::Malformed {
+            line,
+            text: instruction.trim().to_string(),
+        };
+        let tokens: Vec<&str> = instruction.split_whitespace().collect();
+        let ["move", items, "from", from, "to", to] = tokens[..] else {
+            return Err(malformed());
+        };
+        let number = |n: &str| n.parse::<usize>().map_err(|_| malformed());
+        Ok(Instruction {
+            items: number(items)?,
+            from: number(from)?,
+            to: number(to)?,
+            line,
+        })
+    }
+
+    #[cfg(test)]
     fn new(instruction: &str) -> Self {
-        let mut tokens = instruction.split_whitespace();
-        let items: usize = tokens
-            .nth(1)
-            .unwrap_or_default()
-            .parse()
-            .unwrap_or_default();
-        let from: usize = tokens
-            .nth(1)
-            .unwrap_or_default()
-            .parse()
-            .unwrap_or_default();
-        let to: usize = tokens
-            .nth(1)
-            .unwrap_or_default()
-            .parse()
-            .unwrap_or_default();
-        Instruction { items, from, to }
+        Self::parse(instruction, 0).unwrap()
     }
 }
 
-fn initialize_crates(crate_order: &str) -> Vec<Crate> {
-    let mut crates: Vec<Crate> = Vec::new();
-    let size = crate_order
-        .trim()
-        .chars()
-        .last()
-        .unwrap()
-        .to_digit(10)
-        .unwrap();
-    for _ in 0..size {
-        crates.push(Crate::new());
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.items, self.from, self.to)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum InstructionError {
+    Malformed {
+        line: usize,
+        text: String,
+    },
+    UnknownStack {
+        line: usize,
+        stack: usize,
+        stacks: usize,
+    },
+    NotEnoughCrates {
+        line: usize,
+        stack: usize,
+        requested: usize,
+        height: usize,
+    },
+    Crane {
+        line: usize,
+        error: CraneError,
+    },
+}
+
+impl fmt::Display for InstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstructionError::Malformed { line, text } => {
+                write!(f, "line {}: can't read instruction '{}'", line, text)
+            }
+            InstructionError::UnknownStack {
+                line,
+                stack,
+                stacks,
+            } => write!(
+                f,
+                "line {}: there is no stack {}, stacks go from 1 to {}",
+                line, stack, stacks
+            ),
+            InstructionError::NotEnoughCrates {
+                line,
+                stack,
+                requested,
+                height,
+            } => write!(
+                f,
+                "line {}: {} crates requested but stack {} holds {}",
+                line, requested, stack, height
+            ),
+            InstructionError::Crane { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OnError {
+    /// Leave the stacks untouched if any instruction is invalid
+    Abort,
+    /// Carry on without the invalid instructions
+    Skip,
+}
+
+/// Character span of every stack label, in order
+fn label_spans(labels: &str) -> Vec<(usize, usize)> {
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    let mut start: Option<usize> = None;
+    for (i, c) in labels.chars().chain([' ']).enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                spans.push((s, i - 1));
+                start = None;
+            }
+            _ => (),
+        }
     }
-    let ss = crate_order.split("\n").collect::<Vec<&str>>();
+    spans
+}
+
+/// Index of the label closest to a crate's character offset
+fn column_of(spans: &[(usize, usize)], offset: usize) -> usize {
+    spans
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, (start, end))| {
+            if offset < *start {
+                start - offset
+            } else {
+                offset.saturating_sub(*end)
+            }
+        })
+        .map(|(i, _)| i)
+        .unwrap()
+}
+
+fn initialize_crates(crate_order: &str) -> Vec<Crate> {
+    let lines = crate_order.lines().collect::<Vec<&str>>();
+    let (labels, rows) = lines.split_last().unwrap();
+    let spans = label_spans(labels);
+    let mut crates: Vec<Crate> = vec![Crate::new(); spans.len()];
 
-    for line in ss[0..ss.len() - 1].iter().rev() {
-        let ll = line.replace("    ", " [-] ");
-        for (i, token) in ll.split_whitespace().enumerate() {
-            if token != "[-]" {
-                crates[i].push_back(token.chars().nth(1).unwrap_or_default());
+    for line in rows.iter().rev() {
+        let tokens = line.chars().collect::<Vec<char>>();
+        for i in 0..tokens.len().saturating_sub(2) {
+            if tokens[i] == '[' && tokens[i + 2] == ']' {
+                crates[column_of(&spans, i + 1)].push(tokens[i + 1]);
             }
         }
     }
     crates
 }
 
-fn initialize_instructions(instructions: &str) -> Vec<Instruction> {
+/// Parse the instructions, numbering them from `first_line` and skipping
+/// blank lines, along with the lines that can't be read
+fn initialize_instructions(
+    instructions: &str,
+    first_line: usize,
+) -> (Vec<Instruction>, Vec<InstructionError>) {
     let mut ret: Vec<Instruction> = Vec::new();
-    instructions
-        .split('\n')
-        .for_each(|instruction| ret.push(Instruction::new(instruction)));
-    ret
+    let mut malformed: Vec<InstructionError> = Vec::new();
+    for (i, instruction) in instructions.lines().enumerate() {
+        if instruction.trim().is_empty() {
+            continue;
+        }
+        match Instruction::parse(instruction, first_line + i) {
+            Ok(instruction) => ret.push(instruction),
+            Err(error) => malformed.push(error),
+        }
+    }
+    (ret, malformed)
 }
 
-fn read_top(crates: &Vec<Crate>) -> String {
+fn read_top(crates: &[Crate]) -> String {
     let mut output: Vec<char> = Vec::new();
-    crates.iter().for_each(|s| output.push(*s.back().unwrap()));
+    crates
+        .iter()
+        .filter_map(|s| s.last())
+        .for_each(|c| output.push(*c));
     output.iter().collect::<String>()
 }
 
-fn part1(input: &str) -> String {
+/// Render the stacks back into the drawing format, labels included
+fn render(crates: &[Crate]) -> String {
+    let width = 3.max(crates.len().to_string().len() + 1);
+    let height = crates.iter().map(|s| s.len()).max().unwrap_or(0);
+    let mut lines: Vec<String> = Vec::new();
+    for level in (0..height).rev() {
+        let cells = crates
+            .iter()
+            .map(|s| match s.get(level) {
+                Some(c) => format!("{:<width$}", format!("[{}]", c)),
+                None => " ".repeat(width),
+            })
+            .collect::<Vec<String>>();
+        lines.push(cells.join(" "));
+    }
+    let labels = (1..=crates.len())
+        .map(|i| format!(" {:<w$}", i, w = width - 1))
+        .collect::<Vec<String>>();
+    lines.push(labels.join(" "));
+    lines.join("\n")
+}
+
+/// Stacks, instructions and the malformed instructions left out
+type Parsed = (Vec<Crate>, Vec<Instruction>, Vec<InstructionError>);
+
+/// Drawing and instructions. Malformed instructions follow the error policy:
+/// with `OnError::Abort` they are returned as an error, with `OnError::Skip`
+/// they are left out and returned as warnings.
+fn parse_input(input: &str, on_error: OnError) -> Result<Parsed, Vec<InstructionError>> {
     let [st, ins]: [&str; 2] = input
         .split("\n\n")
         .collect::<Vec<&str>>()
         .try_into()
         .unwrap();
+    let first_line = st.lines().count() + 2;
+    let (instructions, malformed) = initialize_instructions(ins, first_line);
+    if on_error == OnError::Abort && !malformed.is_empty() {
+        return Err(malformed);
+    }
+    Ok((initialize_crates(st), instructions, malformed))
+}
+
+/// Drawing and instructions of an input known to be well-formed
+#[cfg(test)]
+fn split_input(input: &str) -> (Vec<Crate>, Vec<Instruction>) {
+    let (crates, instructions, _) = parse_input(input, OnError::Abort).unwrap();
+    (crates, instructions)
+}
 
-    let mut crates: Vec<Crate> = initialize_crates(&st);
-    let instructions: Vec<Instruction> = initialize_instructions(&ins);
-    for Instruction { items, from, to } in instructions.iter() {
-        let mut tmp_stack: Crate = Crate::new();
-        for _ in 0..*items {
-            tmp_stack.push_back(crates[from - 1].pop_back().unwrap());
-            crates[to - 1].push_back(tmp_stack.pop_back().unwrap());
+/// Check that an instruction can be carried out on the current stacks
+fn check(crates: &[Crate], instruction: &Instruction) -> Result<(), InstructionError> {
+    let Instruction {
+        items,
+        from,
+        to,
+        line,
+    } = *instruction;
+    for stack in [from, to] {
+        if stack == 0 || stack > crates.len() {
+            return Err(InstructionError::UnknownStack {
+                line,
+                stack,
+                stacks: crates.len(),
+            });
         }
     }
+    if items > crates[from - 1].len() {
+        return Err(InstructionError::NotEnoughCrates {
+            line,
+            stack: from,
+            requested: items,
+            height: crates[from - 1].len(),
+        });
+    }
+    Ok(())
+}
 
-    read_top(&crates)
+/// Apply every instruction in order. All invalid instructions are reported:
+/// with `OnError::Abort` they are returned as an error and the stacks are left
+/// untouched, with `OnError::Skip` they are left out and returned as warnings.
+fn rearrange(
+    crates: &mut Vec<Crate>,
+    instructions: &[Instruction],
+    crane: &dyn Crane,
+    on_error: OnError,
+) -> Result<Vec<InstructionError>, Vec<InstructionError>> {
+    let mut stacks = crates.clone();
+    let mut errors: Vec<InstructionError> = Vec::new();
+    for instruction in instructions.iter() {
+        let lifted = check(&stacks, instruction).and_then(|_| {
+            crane
+                .lift(&mut stacks, instruction)
+                .map_err(|error| InstructionError::Crane {
+                    line: instruction.line,
+                    error,
+                })
+        });
+        if let Err(error) = lifted {
+            errors.push(error);
+        }
+    }
+    if on_error == OnError::Abort && !errors.is_empty() {
+        return Err(errors);
+    }
+    *crates = stacks;
+    Ok(errors)
 }
 
-fn part2(input: &str) -> String {
-    let [st, ins]: [&str; 2] = input
-        .split("\n\n")
-        .collect::<Vec<&str>>()
-        .try_into()
-        .unwrap();
+/// Top crates after the whole procedure, with the instructions skipped
+type Tops = Result<(String, Vec<InstructionError>), Vec<InstructionError>>;
 
-    let mut crates: Vec<Crate> = initialize_crates(&st);
-    let instructions: Vec<Instruction> = initialize_instructions(&ins);
-    for Instruction { items, from, to } in instructions.iter() {
-        let mut tmp_stack: Crate = Crate::new();
-        for _ in 0..*items {
-            tmp_stack.push_front(crates[from - 1].pop_back().unwrap());
-        }
-        for _ in 0..*items {
-            crates[to - 1].push_back(tmp_stack.pop_front().unwrap());
-        }
+fn tops(
+    crates: &[Crate],
+    instructions: &[Instruction],
+    crane: &dyn Crane,
+    on_error: OnError,
+) -> Tops {
+    let mut crates = crates.to_vec();
+    let warnings = rearrange(&mut crates, instructions, crane, on_error)?;
+    Ok((read_top(&crates), warnings))
+}
+
+fn part1(crates: &[Crate], instructions: &[Instruction], on_error: OnError) -> Tops {
+    tops(crates, instructions, &CrateMover9000, on_error)
+}
+
+fn part2(crates: &[Crate], instructions: &[Instruction], on_error: OnError) -> Tops {
+    tops(crates, instructions, &CrateMover9001, on_error)
+}
+
+fn print_warnings(warnings: &[InstructionError]) {
+    for warning in warnings.iter() {
+        eprintln!("warning: {}, skipped", warning);
     }
+}
 
-    read_top(&crates)
+/// Value of a successful result, or every error printed before exiting
+fn exit_on_errors<T>(result: Result<T, Vec<InstructionError>>) -> T {
+    result.unwrap_or_else(|errors| {
+        for error in errors.iter() {
+            eprintln!("error: {}", error);
+        }
+        process::exit(1);
+    })
+}
+
+/// Value following `flag` on the command line
+fn option<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Crane selected with `--crane NAME`, optionally wrapped by `--max-height H`
+/// and `--overflow reject|spill`
+fn select_crane(args: &[String]) -> Result<Box<dyn Crane>, String> {
+    let name = option(args, "--crane").unwrap_or("9000");
+    let crane = crane_by_name(name).ok_or(format!("unknown crane '{}'", name))?;
+    let Some(max_height) = option(args, "--max-height") else {
+        return Ok(crane);
+    };
+    let max_height = max_height
+        .parse::<usize>()
+        .map_err(|_| format!("invalid maximum height '{}'", max_height))?;
+    let overflow = match option(args, "--overflow").unwrap_or("reject") {
+        "reject" => Overflow::Reject,
+        "spill" => Overflow::Spill,
+        other => return Err(format!("unknown overflow policy '{}'", other)),
+    };
+    Ok(Box::new(HeightLimit {
+        crane,
+        max_height,
+        overflow,
+    }))
 }
 
 fn main() {
-    let input = fs::read_to_string("input.txt").unwrap();
-    println!("{}", part1(&input));
-    println!("{}", part2(&input));
+    let args: Vec<String> = env::args().skip(1).collect();
+    let crane = select_crane(&args).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
+    let on_error = match option(&args, "--on-error").unwrap_or("abort") {
+        "abort" => OnError::Abort,
+        "skip" => OnError::Skip,
+        other => {
+            eprintln!("unknown error policy '{}'", other);
+            process::exit(1);
+        }
+    };
+    // Only the commands working on the puzzle input read it
+    let load = || {
+        let input = fs::read_to_string("input.txt").unwrap_or_else(|error| {
+            eprintln!("input.txt: {}", error);
+            process::exit(1);
+        });
+        let (crates, instructions, warnings) = exit_on_errors(parse_input(&input, on_error));
+        print_warnings(&warnings);
+        (crates, instructions)
+    };
+    match args.first().map(String::as_str) {
+        Some(command @ ("run" | "render")) => {
+            let (mut crates, instructions) = load();
+            let steps = match command {
+                "render" => args
+                    .get(1)
+                    .map_or(0, |n| n.parse::<usize>().unwrap_or_default()),
+                _ => instructions.len(),
+            };
+            let steps = &instructions[..steps.min(instructions.len())];
+            let warnings = exit_on_errors(rearrange(&mut crates, steps, crane.as_ref(), on_error));
+            print_warnings(&warnings);
+            match command {
+                "run" => println!("{}", read_top(&crates)),
+                _ => println!("{}", render(&crates)),
+            }
+        }
+        Some("bench") => {
+            let moves = args
+                .get(1)
+                .map_or(1_000_000, |n| n.parse::<usize>().unwrap_or(1_000_000));
+            bench::run(moves);
+        }
+        Some("solve") => {
+            let (Some(start), Some(target)) = (args.get(1), args.get(2)) else {
+                eprintln!("usage: solve START TARGET [--crane NAME] [--limit N]");
+                process::exit(1);
+            };
+            let drawing = |path: &str| {
+                let file = fs::read_to_string(path).unwrap();
+                initialize_crates(file.split("\n\n").next().unwrap())
+            };
+            let limit = option(&args, "--limit")
+                .map_or(1_000_000, |n| n.parse::<usize>().unwrap_or(1_000_000));
+            let start = drawing(start);
+            match solver::solve(&start, &drawing(target), crane.as_ref(), limit) {
+                Ok(solution) => print!("{}", solver::format_procedure(&start, &solution)),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    process::exit(1);
+                }
+            }
+        }
+        Some("optimise") => {
+            let (crates, instructions) = load();
+            let optimised = optimise::optimise(&instructions, crane.as_ref());
+            let equivalent = exit_on_errors(optimise::equivalent(
+                &crates,
+                &instructions,
+                &optimised,
+                crane.as_ref(),
+            ));
+            eprintln!(
+                "{} instructions down to {}, same final stacks: {}",
+                instructions.len(),
+                optimised.len(),
+                equivalent
+            );
+            print!("{}", solver::format_procedure(&crates, &optimised));
+        }
+        Some("export") => {
+            let (crates, instructions) = load();
+            let dir = args.get(1).map_or("frames", String::as_str);
+            match animation::export(Path::new(dir), crates, &instructions, crane.as_ref()) {
+                Ok(frames) => println!("{} frames and animation.svg written to {}", frames, dir),
+                Err(error) => {
+                    eprintln!("{}", error);
+                    process::exit(1);
+                }
+            }
+        }
+        Some("step") => {
+            let (crates, instructions) = load();
+            let mut stepper = stepper::Stepper::new(crates, &instructions, crane.as_ref());
+            stepper::interact(&mut stepper, io::stdin().lock(), &mut io::stdout()).unwrap();
+        }
+        _ => {
+            let (crates, instructions) = load();
+            for part in [part1, part2] {
+                let (top, warnings) = exit_on_errors(part(&crates, &instructions, on_error));
+                print_warnings(&warnings);
+                println!("{}", top);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -124,12 +489,112 @@ mod tests {
     #[test]
     fn part1_test() {
         let input = fs::read_to_string("test.txt").unwrap();
-        assert_eq!("CMZ", part1(&input));
+        let (crates, instructions) = split_input(&input);
+        assert_eq!(
+            Ok(("CMZ".to_string(), vec![])),
+            part1(&crates, &instructions, OnError::Abort)
+        );
     }
 
     #[test]
     fn part2_test() {
         let input = fs::read_to_string("test.txt").unwrap();
-        assert_eq!("MCD", part2(&input));
+        let (crates, instructions) = split_input(&input);
+        assert_eq!(
+            Ok(("MCD".to_string(), vec![])),
+            part2(&crates, &instructions, OnError::Abort)
+        );
+    }
+
+    #[test]
+    fn wide_drawing_test() {
+        let drawing = [
+            "                                            [L]",
+            "[A] [B] [C] [D] [E] [F] [G] [H] [I] [J] [K] [M]",
+            " 1   2   3   4   5   6   7   8   9  10  11  12 ",
+        ]
+        .join("\n");
+        let crates = initialize_crates(&drawing);
+        assert_eq!(12, crates.len());
+        assert_eq!("ABCDEFGHIJKL", read_top(&crates));
+        assert_eq!(2, crates[11].len());
+    }
+
+    #[test]
+    fn render_test() {
+        let input = fs::read_to_string("test.txt").unwrap();
+        let drawing = input.split("\n\n").next().unwrap();
+        let (mut crates, instructions) = split_input(&input);
+        assert_eq!(drawing, render(&crates));
+
+        CrateMover9000.lift(&mut crates, &instructions[0]).unwrap();
+        assert_eq!(
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ",
+            render(&crates)
+        );
+        assert_eq!(crates, initialize_crates(&render(&crates)));
+    }
+
+    #[test]
+    fn render_wide_test() {
+        let mut crates: Vec<Crate> = vec![Crate::new(); 120];
+        crates[0].push('A');
+        crates[99].extend(['B', 'C']);
+        crates[119].push('D');
+        let drawing = render(&crates);
+        assert_eq!(crates, initialize_crates(&drawing));
+        assert!(drawing.ends_with(" 119  120"));
+    }
+
+    #[test]
+    fn validation_test() {
+        let input = fs::read_to_string("test.txt").unwrap();
+        let input = input.trim_end().to_string() + "\nmove 4 from 2 to 1\nmove 1 from 0 to 3\n";
+        let (mut crates, instructions) = split_input(&input);
+        let errors =
+            rearrange(&mut crates, &instructions, &CrateMover9000, OnError::Abort).unwrap_err();
+        assert_eq!(
+            vec![
+                InstructionError::NotEnoughCrates {
+                    line: 10,
+                    stack: 2,
+                    requested: 4,
+                    height: 1,
+                },
+                InstructionError::UnknownStack {
+                    line: 11,
+                    stack: 0,
+                    stacks: 3,
+                },
+            ],
+            errors
+        );
+        assert_eq!("NDP", read_top(&crates));
+
+        let warnings =
+            rearrange(&mut crates, &instructions, &CrateMover9000, OnError::Skip).unwrap();
+        assert_eq!(2, warnings.len());
+        assert_eq!("CMZ", read_top(&crates));
+
+        let input = fs::read_to_string("test.txt").unwrap();
+        let input = input.trim_end().to_string() + "\nmove x from 1 to 3\nmove 1 from 2\n";
+        assert_eq!(
+            Err(vec![
+                InstructionError::Malformed {
+                    line: 10,
+                    text: "move x from 1 to 3".to_string(),
+                },
+                InstructionError::Malformed {
+                    line: 11,
+                    text: "move 1 from 2".to_string(),
+                },
+            ]),
+            parse_input(&input, OnError::Abort)
+        );
+        let (mut crates, instructions, warnings) = parse_input(&input, OnError::Skip).unwrap();
+        assert_eq!(4, instructions.len());
+        assert_eq!(2, warnings.len());
+        rearrange(&mut crates, &instructions, &CrateMover9000, OnError::Abort).unwrap();
+        assert_eq!("CMZ", read_top(&crates));
     }
 }
@@ -0,0 +1,140 @@
+use crate::crane::Crane;
+use crate::{rearrange, Crate, Instruction, InstructionError, OnError};
+
+/// Shorter procedure with the same effect: no-op moves are dropped, moves
+/// undone by the next one are removed and consecutive moves are merged where
+/// the crane allows it. Every reduction is retried against the move before,
+/// so whole cancelling sequences disappear.
+pub fn optimise(instructions: &[Instruction], crane: &dyn Crane) -> Vec<Instruction> {
+    let mut kept: Vec<Instruction> = Vec::new();
+    for instruction in instructions.iter() {
+        let mut current = *instruction;
+        while !crane.is_noop(&current) {
+            let Some(last) = kept.last() else {
+                kept.push(current);
+                break;
+            };
+            if crane.cancels(last, &current) {
+                kept.pop();
+                break;
+            }
+            match crane.merge(last, &current) {
+                Some(merged) => {
+                    current = merged;
+                    kept.pop();
+                }
+                None => {
+                    kept.push(current);
+                    break;
+                }
+            }
+        }
+    }
+    kept
+}
+
+/// Simulate both procedures and compare the final stacks
+pub fn equivalent(
+    crates: &[Crate],
+    original: &[Instruction],
+    optimised: &[Instruction],
+    crane: &dyn Crane,
+) -> Result<bool, Vec<InstructionError>> {
+    let mut before = crates.to_vec();
+    rearrange(&mut before, original, crane, OnError::Abort)?;
+    let mut after = crates.to_vec();
+    rearrange(&mut after, optimised, crane, OnError::Abort)?;
+    Ok(before == after)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::random_procedure;
+    use crate::crane::{crane_by_name, CrateMover9000, CrateMover9001, HeightLimit, Overflow};
+    use crate::optimise::*;
+
+    fn procedure(lines: &[&str]) -> Vec<Instruction> {
+        lines.iter().map(|l| Instruction::new(l)).collect()
+    }
+
+    #[test]
+    fn optimise_test() {
+        let instructions = procedure(&[
+            "move 2 from 1 to 3",
+            "move 2 from 3 to 1",
+            "move 1 from 1 to 2",
+            "move 1 from 2 to 3",
+            "move 0 from 1 to 2",
+            "move 1 from 3 to 3",
+            "move 2 from 2 to 3",
+            "move 3 from 2 to 3",
+        ]);
+        assert_eq!(
+            procedure(&["move 1 from 1 to 3", "move 5 from 2 to 3"]),
+            optimise(&instructions, &CrateMover9000)
+        );
+        assert_eq!(
+            procedure(&[
+                "move 1 from 1 to 3",
+                "move 2 from 2 to 3",
+                "move 3 from 2 to 3"
+            ]),
+            optimise(&instructions, &CrateMover9001)
+        );
+        let nested = procedure(&[
+            "move 1 from 1 to 2",
+            "move 3 from 2 to 3",
+            "move 3 from 3 to 2",
+            "move 1 from 2 to 1",
+        ]);
+        assert!(optimise(&nested, &CrateMover9001).is_empty());
+    }
+
+    #[test]
+    fn equivalence_test() {
+        let (crates, mut instructions) = random_procedure(4, 6, 300, 11);
+        // Sprinkle moves that are undone and done again straight away
+        for i in (0..instructions.len()).step_by(7).rev() {
+            let undo = Instruction {
+                from: instructions[i].to,
+                to: instructions[i].from,
+                ..instructions[i]
+            };
+            instructions.splice(i + 1..i + 1, [undo, instructions[i]]);
+        }
+        for name in ["9000", "9001", "batch:2"] {
+            let crane = crane_by_name(name).unwrap();
+            let optimised = optimise(&instructions, crane.as_ref());
+            assert!(optimised.len() < instructions.len());
+            assert_eq!(
+                Ok(true),
+                equivalent(&crates, &instructions, &optimised, crane.as_ref())
+            );
+        }
+        let crane = HeightLimit {
+            crane: Box::new(CrateMover9001),
+            max_height: 24,
+            overflow: Overflow::Spill,
+        };
+        let optimised = optimise(&instructions, &crane);
+        assert_eq!(
+            Ok(true),
+            equivalent(&crates, &instructions, &optimised, &crane)
+        );
+
+        // A stack drawn above the limit spills even onto itself
+        let crane = HeightLimit {
+            crane: Box::new(CrateMover9000),
+            max_height: 2,
+            overflow: Overflow::Spill,
+        };
+        let crates: Vec<Crate> = vec![vec!['A', 'B', 'C'], vec![]];
+        let instructions = procedure(&["move 1 from 1 to 1"]);
+        let optimised = optimise(&instructions, &crane);
+        assert_eq!(instructions, optimised);
+        assert_eq!(
+            Ok(true),
+            equivalent(&crates, &instructions, &optimised, &crane)
+        );
+    }
+}
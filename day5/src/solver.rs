@@ -0,0 +1,180 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use crate::crane::Crane;
+use crate::{render, Crate, Instruction};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SolveError {
+    StackCount {
+        start: usize,
+        target: usize,
+    },
+    DifferentCrates,
+    /// More than the allowed number of arrangements were explored
+    SearchLimit(usize),
+    Unreachable,
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::StackCount { start, target } => write!(
+                f,
+                "the starting drawing has {} stacks but the target has {}",
+                start, target
+            ),
+            SolveError::DifferentCrates => {
+                write!(f, "the two drawings don't hold the same crates")
+            }
+            SolveError::SearchLimit(limit) => {
+                write!(f, "no procedure found within {} arrangements", limit)
+            }
+            SolveError::Unreachable => write!(f, "the target can't be reached with this crane"),
+        }
+    }
+}
+
+fn sorted_crates(crates: &[Crate]) -> Vec<char> {
+    let mut all: Vec<char> = crates.iter().flatten().copied().collect();
+    all.sort();
+    all
+}
+
+/// Breadth-first search over arrangements for a shortest procedure turning
+/// `start` into `target`. Every arrangement is expanded once, and the search
+/// gives up after `limit` arrangements, so it is meant for small drawings.
+/// Moves that undo the previous one or could be merged with it are never
+/// tried, since a shorter procedure reaches the same arrangement.
+pub fn solve(
+    start: &[Crate],
+    target: &[Crate],
+    crane: &dyn Crane,
+    limit: usize,
+) -> Result<Vec<Instruction>, SolveError> {
+    if start.len() != target.len() {
+        return Err(SolveError::StackCount {
+            start: start.len(),
+            target: target.len(),
+        });
+    }
+    if sorted_crates(start) != sorted_crates(target) {
+        return Err(SolveError::DifferentCrates);
+    }
+
+    // Index of every arrangement reached, and for each index the one it came from and the move used
+    let mut seen: HashMap<Vec<Crate>, usize> = HashMap::from([(start.to_vec(), 0)]);
+    let mut states: Vec<(usize, Option<Instruction>)> = vec![(0, None)];
+    let mut queue: VecDeque<(Vec<Crate>, usize)> = VecDeque::from([(start.to_vec(), 0)]);
+    let mut found = (start == target).then_some(0);
+
+    while let (None, Some((arrangement, i))) = (found, queue.pop_front()) {
+        let previous = states[i].1;
+        for from in 1..=target.len() {
+            for to in (1..=target.len()).filter(|to| *to != from) {
+                for items in 1..=arrangement[from - 1].len() {
+                    let instruction = Instruction {
+                        items,
+                        from,
+                        to,
+                        line: 0,
+                    };
+                    if previous.is_some_and(|p| {
+                        crane.cancels(&p, &instruction) || crane.merge(&p, &instruction).is_some()
+                    }) {
+                        continue;
+                    }
+                    let mut crates = arrangement.clone();
+                    if crane.lift(&mut crates, &instruction).is_err() || seen.contains_key(&crates)
+                    {
+                        continue;
+                    }
+                    if seen.len() == limit {
+                        return Err(SolveError::SearchLimit(limit));
+                    }
+                    if crates == target {
+                        found = Some(states.len());
+                    }
+                    seen.insert(crates.clone(), states.len());
+                    states.push((i, Some(instruction)));
+                    queue.push_back((crates, states.len() - 1));
+                }
+            }
+        }
+    }
+
+    let mut i = found.ok_or(SolveError::Unreachable)?;
+    let mut instructions: Vec<Instruction> = Vec::new();
+    while let Some(instruction) = states[i].1 {
+        instructions.push(instruction);
+        i = states[i].0;
+    }
+    instructions.reverse();
+    for (n, instruction) in instructions.iter_mut().enumerate() {
+        instruction.line = start.iter().map(|s| s.len()).max().unwrap_or(0) + 3 + n;
+    }
+    Ok(instructions)
+}
+
+/// Drawing and instructions in the day5 input format
+pub fn format_procedure(start: &[Crate], instructions: &[Instruction]) -> String {
+    let mut output = render(start) + "\n\n";
+    for instruction in instructions.iter() {
+        output += &format!("{}\n", instruction);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crane::{CrateMover9000, CrateMover9001};
+    use crate::solver::*;
+    use crate::{initialize_crates, rearrange, split_input, OnError};
+    use std::fs;
+
+    #[test]
+    fn solve_test() {
+        let input = fs::read_to_string("test.txt").unwrap();
+        let (start, instructions) = split_input(&input);
+        for crane in [&CrateMover9000 as &dyn Crane, &CrateMover9001] {
+            let mut target = start.clone();
+            rearrange(&mut target, &instructions, crane, OnError::Abort).unwrap();
+
+            let solution = solve(&start, &target, crane, 100_000).unwrap();
+            assert!(solution.len() <= instructions.len());
+
+            let procedure = format_procedure(&start, &solution);
+            let (mut crates, parsed) = split_input(&procedure);
+            assert_eq!(solution, parsed);
+            rearrange(&mut crates, &parsed, crane, OnError::Abort).unwrap();
+            assert_eq!(target, crates);
+        }
+    }
+
+    #[test]
+    fn shortest_test() {
+        let start = initialize_crates("[A]    \n[B]    \n 1   2 ");
+        let target = initialize_crates("    [B]\n    [A]\n 1   2 ");
+        assert_eq!(
+            1,
+            solve(&start, &target, &CrateMover9000, 1_000)
+                .unwrap()
+                .len()
+        );
+        assert_eq!(
+            2,
+            solve(&start, &target, &CrateMover9001, 1_000)
+                .unwrap()
+                .len()
+        );
+        assert!(solve(&start, &start, &CrateMover9001, 1_000)
+            .unwrap()
+            .is_empty());
+
+        let other = initialize_crates("    [C]\n    [A]\n 1   2 ");
+        assert_eq!(
+            Err(SolveError::DifferentCrates),
+            solve(&start, &other, &CrateMover9000, 1_000)
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use std::io::{self, BufRead, Write};
+
+use crate::crane::Crane;
+use crate::{check, render, Crate, Instruction, InstructionError};
+
+/// Walks through a procedure one instruction at a time, keeping every state
+/// reached so far so that moves can be undone and redone
+pub struct Stepper<'a> {
+    instructions: &'a [Instruction],
+    crane: &'a dyn Crane,
+    /// `states[k]` holds the stacks after the first `k` instructions
+    states: Vec<Vec<Crate>>,
+    position: usize,
+    failed: bool,
+}
+
+impl<'a> Stepper<'a> {
+    pub fn new(crates: Vec<Crate>, instructions: &'a [Instruction], crane: &'a dyn Crane) -> Self {
+        Stepper {
+            instructions,
+            crane,
+            states: vec![crates],
+            position: 0,
+            failed: false,
+        }
+    }
+
+    /// Number of instructions applied to the current state
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn state(&self) -> &[Crate] {
+        &self.states[self.position]
+    }
+
+    /// Instruction that led to the current state
+    pub fn last_instruction(&self) -> Option<&Instruction> {
+        self.position.checked_sub(1).map(|i| &self.instructions[i])
+    }
+
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// Apply the next instruction, or fail without moving if it can't be carried out
+    pub fn step(&mut self) -> Option<Result<&[Crate], InstructionError>> {
+        if self.position == self.instructions.len() {
+            return None;
+        }
+        if self.position + 1 == self.states.len() {
+            let instruction = &self.instructions[self.position];
+            let mut crates = self.states[self.position].clone();
+            if let Err(error) = check(&crates, instruction) {
+                return Some(Err(error));
+            }
+            if let Err(error) = self.crane.lift(&mut crates, instruction) {
+                return Some(Err(InstructionError::Crane {
+                    line: instruction.line,
+                    error,
+                }));
+            }
+            self.states.push(crates);
+        }
+        self.position += 1;
+        Some(Ok(self.state()))
+    }
+
+    pub fn undo(&mut self) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+        self.position -= 1;
+        true
+    }
+
+    /// Go forward again through states left with `undo`
+    pub fn redo(&mut self) -> bool {
+        if self.position + 1 >= self.states.len() {
+            return false;
+        }
+        self.position += 1;
+        true
+    }
+
+    /// Move to the state after the first `n` instructions
+    pub fn jump(&mut self, n: usize) -> Result<&[Crate], InstructionError> {
+        let n = n.min(self.instructions.len());
+        if n < self.states.len() {
+            self.position = n;
+        }
+        while self.position < n {
+            if let Some(Err(error)) = self.step() {
+                return Err(error);
+            }
+        }
+        Ok(self.state())
+    }
+}
+
+/// Yields the stacks after each move, stopping after the first invalid instruction
+impl Iterator for Stepper<'_> {
+    type Item = Result<Vec<Crate>, InstructionError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let next = self.step()?.map(|crates| crates.to_vec());
+        self.failed = next.is_err();
+        Some(next)
+    }
+}
+
+const HELP: &str = "n: next, u: undo, r: redo, j N: jump to instruction N, q: quit";
+
+fn show<W: Write>(stepper: &Stepper, output: &mut W) -> io::Result<()> {
+    match stepper.last_instruction() {
+        Some(instruction) => writeln!(
+            output,
+            "After instruction {}/{} (line {}): {}",
+            stepper.position(),
+            stepper.len(),
+            instruction.line,
+            instruction
+        )?,
+        None => writeln!(output, "Starting drawing, {} instructions", stepper.len())?,
+    }
+    writeln!(output, "{}", render(stepper.state()))
+}
+
+/// Small terminal front-end reading one command per line
+pub fn interact<R: BufRead, W: Write>(
+    stepper: &mut Stepper,
+    input: R,
+    output: &mut W,
+) -> io::Result<()> {
+    writeln!(output, "{}", HELP)?;
+    show(stepper, output)?;
+    for line in input.lines() {
+        let line = line?;
+        let mut tokens = line.split_whitespace();
+        let result = match (tokens.next(), tokens.next()) {
+            (None | Some("n"), _) => match stepper.step() {
+                Some(result) => result.map(|_| ()),
+                None => {
+                    writeln!(output, "End of the procedure")?;
+                    continue;
+                }
+            },
+            (Some("u"), _) => {
+                if !stepper.undo() {
+                    writeln!(output, "Nothing to undo")?;
+                }
+                Ok(())
+            }
+            (Some("r"), _) => {
+                if !stepper.redo() {
+                    writeln!(output, "Nothing to redo")?;
+                }
+                Ok(())
+            }
+            (Some("j"), Some(n)) => match n.parse::<usize>() {
+                Ok(n) => stepper.jump(n).map(|_| ()),
+                Err(_) => {
+                    writeln!(output, "Invalid instruction number '{}'", n)?;
+                    continue;
+                }
+            },
+            (Some("q"), _) => break,
+            _ => {
+                writeln!(output, "{}", HELP)?;
+                continue;
+            }
+        };
+        if let Err(error) = result {
+            writeln!(output, "Stopped: {}", error)?;
+        }
+        show(stepper, output)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crane::CrateMover9000;
+    use crate::stepper::*;
+    use crate::{read_top, split_input};
+    use std::fs;
+
+    #[test]
+    fn stepper_test() {
+        let input = fs::read_to_string("test.txt").unwrap();
+        let (crates, instructions) = split_input(&input);
+        let mut stepper = Stepper::new(crates, &instructions, &CrateMover9000);
+
+        assert_eq!("DCP", read_top(stepper.step().unwrap().unwrap()));
+        assert!(stepper.undo());
+        assert!(!stepper.undo());
+        assert_eq!("NDP", read_top(stepper.state()));
+        assert!(stepper.redo());
+        assert!(!stepper.redo());
+        assert_eq!("CMZ", read_top(stepper.jump(4).unwrap()));
+        assert_eq!("DCP", read_top(stepper.jump(1).unwrap()));
+        assert!(stepper.redo());
+        assert_eq!(2, stepper.position());
+
+        let tops: Vec<String> = Stepper::new(split_input(&input).0, &instructions, &CrateMover9000)
+            .map(|crates| read_top(&crates.unwrap()))
+            .collect();
+        assert_eq!(vec!["DCP", "CZ", "MZ", "CMZ"], tops);
+    }
+
+    #[test]
+    fn interact_test() {
+        let input = fs::read_to_string("test.txt").unwrap();
+        let (crates, instructions) = split_input(&input);
+        let mut stepper = Stepper::new(crates, &instructions, &CrateMover9000);
+        let mut output: Vec<u8> = Vec::new();
+        interact(&mut stepper, "n\nj 3\nu\nq\nn\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("After instruction 3/4 (line 8): move 2 from 2 to 1"));
+        assert_eq!(2, stepper.position());
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::check_marker;
+
+/// Xorshift generator, enough to build reproducible buffers
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+/// Random buffer over the first `alphabet` printable ASCII characters
+pub fn random_buffer(size: usize, alphabet: usize, seed: u64) -> String {
+    let mut rng = Rng(seed | 1);
+    (0..size)
+        .map(|_| (b'!' + rng.below(alphabet) as u8) as char)
+        .collect()
+}
+
+/// Counts the characters of every window from scratch, the way day6 used to,
+/// returning 0 when there is no marker
+pub fn check_marker_per_window(data_buff: &str, distinct: usize) -> usize {
+    let mut index: usize = 0;
+    for i in 0..data_buff.chars().count() - distinct {
+        let mut m: HashMap<char, i32> = HashMap::new();
+        let mut found: bool = true;
+        let slice = &data_buff[i..i + distinct];
+        slice.chars().for_each(|c| {
+            *m.entry(c).or_insert(0) += 1;
+        });
+
+        m.values().for_each(|v| {
+            if *v > 1 {
+                found = false;
+            }
+        });
+
+        if found {
+            index = i + distinct;
+            break;
+        }
+    }
+    index
+}
+
+fn time<F: FnOnce() -> usize>(f: F) -> (usize, Duration) {
+    let start = Instant::now();
+    let marker = f();
+    (marker, start.elapsed())
+}
+
+/// Scan buffers holding no marker at all, so that both versions go through
+/// the whole buffer, for the window sizes of both parts and a large one
+pub fn run(size: usize) {
+    for distinct in [4, 14, 1000] {
+        if distinct > size {
+            println!("window {:>4}: skipped, longer than the buffer", distinct);
+            continue;
+        }
+        let alphabet = (distinct - 1).min(94);
+        let buffer = random_buffer(size, alphabet, distinct as u64);
+        let (marker, sliding) = time(|| check_marker(&buffer, distinct).unwrap_or(0));
+        let (reference, per_window) = time(|| check_marker_per_window(&buffer, distinct));
+        assert_eq!(reference, marker);
+        println!(
+            "window {:>4}, {} characters: {:?} sliding, {:?} per window",
+            distinct, size, sliding, per_window
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::*;
+
+    #[test]
+    fn sliding_window_test() {
+        for (distinct, alphabet) in [(4, 4), (14, 20), (14, 13), (30, 60)] {
+            let buffer = random_buffer(5_000, alphabet, 17);
+            assert_eq!(
+                check_marker_per_window(&buffer, distinct),
+                check_marker(&buffer, distinct).unwrap_or(0)
+            );
+        }
+    }
+}
@@ -1,34 +1,253 @@
-use std::collections::HashMap;
-use std::fs;
-
-fn check_marker(data_buff: &str, distinct: usize) -> usize {
-    let mut index: usize = 0;
-    for i in 0..data_buff.chars().count() - distinct {
-        let mut m: HashMap<char, i32> = HashMap::new();
-        let mut found: bool = true;
-        let slice = &data_buff[i..i + distinct];
-        slice.chars().for_each(|c| {
-            *m.entry(c).or_insert(0) += 1;
-        });
-
-        m.values().for_each(|v| {
-            if *v > 1 {
-                found = false;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{self, Read};
+use std::time::Instant;
+use std::{env, fs, process, thread};
+
+mod bench;
+mod markers;
+mod parallel;
+mod predicate;
+mod stream;
+
+/// Number of items read up to the end of the first window of `distinct`
+/// pairwise different items
+pub fn find_marker<T, I>(stream: I, distinct: usize) -> Option<usize>
+where
+    T: Eq + Hash,
+    I: IntoIterator<Item = T>,
+{
+    if distinct == 0 {
+        return Some(0);
+    }
+    markers::markers(stream, distinct).next()
+}
+
+/// Marker position counted in characters
+fn check_marker(data_buff: &str, distinct: usize) -> Option<usize> {
+    find_marker(data_buff.chars(), distinct)
+}
+
+/// Marker position counted in bytes
+fn check_marker_bytes(data_buff: &[u8], distinct: usize) -> Option<usize> {
+    find_marker(data_buff.iter(), distinct)
+}
+
+fn print_marker(part: usize, marker: Option<usize>) {
+    match marker {
+        Some(marker) => println!("Part {}: {}", part, marker),
+        None => println!("Part {}: no marker", part),
+    }
+}
+
+fn option<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// `scan [PATH|-] [--windows 4,14]`: stream a file, or stdin with `-`,
+/// printing markers as soon as they are read
+fn scan(args: &[String]) {
+    let windows: Vec<usize> = match option(args, "--windows")
+        .unwrap_or("4,14")
+        .split(',')
+        .map(|w| w.trim().parse::<usize>())
+        .collect()
+    {
+        Ok(windows) => windows,
+        Err(_) => {
+            eprintln!("--windows takes comma separated window sizes");
+            process::exit(1);
+        }
+    };
+    let path = args
+        .first()
+        .filter(|a| !a.starts_with("--"))
+        .map_or("input.txt", String::as_str);
+    let reader: Box<dyn Read> = if path == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        match File::open(path) {
+            Ok(file) => Box::new(file),
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                process::exit(1);
+            }
+        }
+    };
+    let mut found = 0;
+    for marker in stream::Scanner::new(reader, &windows) {
+        match marker {
+            Ok(marker) => {
+                found += 1;
+                println!(
+                    "Window {}: marker after {} bytes",
+                    marker.distinct, marker.offset
+                );
             }
-        });
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                process::exit(1);
+            }
+        }
+    }
+    if found < windows.len() {
+        println!("{} window sizes without a marker", windows.len() - found);
+    }
+}
 
-        if found {
-            index = i + distinct;
-            break;
+fn window(args: &[String], default: usize) -> usize {
+    match option(args, "--window").map(|w| w.parse::<usize>()) {
+        None => default,
+        Some(Ok(w)) if w > 0 => w,
+        Some(_) => {
+            eprintln!("--window takes a size of at least 1");
+            process::exit(1);
         }
     }
-    index
+}
+
+/// `markers [--window N] [--disjoint]`: every marker position in input.txt
+fn print_markers(args: &[String]) {
+    let input = fs::read_to_string("input.txt").unwrap();
+    let distinct = window(args, 4);
+    let found: Vec<String> = if args.iter().any(|a| a == "--disjoint") {
+        markers::boundaries(input.chars(), distinct)
+            .map(|m| m.to_string())
+            .collect()
+    } else {
+        markers::markers(input.chars(), distinct)
+            .map(|m| m.to_string())
+            .collect()
+    };
+    println!("{} markers: {}", found.len(), found.join(" "));
+}
+
+/// `packets [--window N]`: split input.txt on its start-of-packet markers
+fn print_packets(args: &[String]) {
+    let input = fs::read("input.txt").unwrap();
+    for packet in markers::packets(&input, window(args, 4)) {
+        println!(
+            "{:>6}: {}",
+            packet.offset,
+            String::from_utf8_lossy(packet.payload).trim_end()
+        );
+    }
+}
+
+fn predicate(name: &str) -> Box<dyn predicate::Predicate> {
+    match predicate::predicate_by_name(name) {
+        Some(predicate) => predicate,
+        None => {
+            eprintln!(
+                "Unknown predicate '{}', expected distinct:K, at-least:M/K, alphabet:K:LETTERS or pattern:BYTES",
+                name
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// `parallel [PATH] [--threads N] [--predicate SPEC] [--check]`: search a
+/// large file on several threads; `--check` also runs a sequential search and
+/// fails if the two disagree
+fn search_parallel(args: &[String]) {
+    let path = args
+        .first()
+        .filter(|a| !a.starts_with("--"))
+        .map_or("input.txt", String::as_str);
+    let predicate = predicate(option(args, "--predicate").unwrap_or("distinct:14"));
+    let threads = match option(args, "--threads").map(|t| t.parse::<usize>()) {
+        None => thread::available_parallelism().map_or(1, |n| n.get()),
+        Some(Ok(threads)) if threads > 0 => threads,
+        Some(_) => {
+            eprintln!("--threads takes a number of at least 1");
+            process::exit(1);
+        }
+    };
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            process::exit(1);
+        }
+    };
+    let start = Instant::now();
+    let marker = parallel::find_parallel(&data, predicate.as_ref(), threads);
+    let parallel_time = start.elapsed();
+    match marker {
+        Some(marker) => println!("Marker: {}", marker),
+        None => println!("Marker: none"),
+    }
+    if !args.iter().any(|a| a == "--check") {
+        println!("{} threads: {:?}", threads, parallel_time);
+        return;
+    }
+    let start = Instant::now();
+    let sequential = predicate::find(&data, predicate.as_ref());
+    let sequential_time = start.elapsed();
+    println!(
+        "{} threads: {:?}, sequential: {:?}",
+        threads, parallel_time, sequential_time
+    );
+    if sequential != marker {
+        eprintln!(
+            "parallel search found {:?} but sequential search found {:?}",
+            marker, sequential
+        );
+        process::exit(1);
+    }
 }
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("bench") => {
+            let size = args
+                .get(1)
+                .map_or(100_000, |n| n.parse::<usize>().unwrap_or(100_000));
+            bench::run(size);
+            return;
+        }
+        Some("scan") => {
+            scan(&args[1..]);
+            return;
+        }
+        Some("markers") => {
+            print_markers(&args[1..]);
+            return;
+        }
+        Some("packets") => {
+            print_packets(&args[1..]);
+            return;
+        }
+        Some("parallel") => {
+            search_parallel(&args[1..]);
+            return;
+        }
+        _ => {}
+    }
+    if let Some(name) = option(&args, "--predicate") {
+        let predicate = predicate(name);
+        let input = fs::read("input.txt").unwrap();
+        match predicate::find(&input, predicate.as_ref()) {
+            Some(marker) => println!("Marker: {}", marker),
+            None => println!("Marker: none"),
+        }
+        return;
+    }
+    // `--bytes` counts offsets in bytes, which also works on input that isn't UTF-8
+    if args.iter().any(|a| a == "--bytes") {
+        let input = fs::read("input.txt").unwrap();
+        print_marker(1, check_marker_bytes(&input, 4));
+        print_marker(2, check_marker_bytes(&input, 14));
+        return;
+    }
     let input = fs::read_to_string("input.txt").unwrap();
-    println!("Part 1: {}", check_marker(&input, 4));
-    println!("Part 2: {}", check_marker(&input, 14));
+    print_marker(1, check_marker(&input, 4));
+    print_marker(2, check_marker(&input, 14));
 }
 
 #[cfg(test)]
@@ -45,7 +264,7 @@ mod tests {
         ];
         let markers: Vec<usize> = vec![7, 5, 6, 10, 11];
         for (b, m) in data_buffers.iter().zip(markers.iter()) {
-            assert_eq!(*m, check_marker(b, 4));
+            assert_eq!(Some(*m), check_marker(b, 4));
         }
     }
 
@@ -60,7 +279,23 @@ mod tests {
         ];
         let markers: Vec<usize> = vec![19, 23, 23, 29, 26];
         for (b, m) in data_buffers.iter().zip(markers.iter()) {
-            assert_eq!(*m, check_marker(b, 14));
+            assert_eq!(Some(*m), check_marker(b, 14));
         }
     }
+
+    #[test]
+    fn edge_cases_test() {
+        assert_eq!(None, check_marker("", 4));
+        assert_eq!(None, check_marker("abc", 4));
+        assert_eq!(None, check_marker("aaaaaaa", 2));
+        assert_eq!(Some(0), check_marker("", 0));
+        // The marker can be the very last window
+        assert_eq!(Some(4), check_marker("abcd", 4));
+        assert_eq!(Some(6), check_marker("aaabcd", 4));
+        // Offsets count characters for text and bytes for raw data
+        assert_eq!(Some(5), check_marker("ééabc", 4));
+        assert_eq!(Some(6), check_marker_bytes("ééabc".as_bytes(), 4));
+        assert_eq!(Some(3), check_marker("日本語", 3));
+        assert_eq!(Some(3), check_marker_bytes(&[0, 255, 7], 3));
+    }
 }
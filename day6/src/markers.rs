@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Iterator over marker positions, each being the number of items read up
+/// to the end of a window of pairwise different items
+pub struct Markers<T, I> {
+    items: I,
+    distinct: usize,
+    /// Restart the search after each marker instead of sliding on
+    disjoint: bool,
+    last_seen: HashMap<T, usize>,
+    start: usize,
+    position: usize,
+}
+
+impl<T: Eq + Hash, I: Iterator<Item = T>> Iterator for Markers<T, I> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        for item in self.items.by_ref() {
+            let position = self.position;
+            self.position += 1;
+            if let Some(previous) = self.last_seen.insert(item, position) {
+                self.start = self.start.max(previous + 1);
+            }
+            if position + 1 - self.start >= self.distinct {
+                if self.disjoint {
+                    self.start = position + 1;
+                }
+                return Some(position + 1);
+            }
+        }
+        None
+    }
+}
+
+fn new_markers<T, I>(stream: I, distinct: usize, disjoint: bool) -> Markers<T, I::IntoIter>
+where
+    I: IntoIterator<Item = T>,
+{
+    assert!(distinct > 0, "markers need a window of at least one item");
+    Markers {
+        items: stream.into_iter(),
+        distinct,
+        disjoint,
+        last_seen: HashMap::new(),
+        start: 0,
+        position: 0,
+    }
+}
+
+/// Every position ending a window of `distinct` different items, so a run
+/// of `distinct + n` different items holds `n + 1` markers
+pub fn markers<T, I>(stream: I, distinct: usize) -> Markers<T, I::IntoIter>
+where
+    I: IntoIterator<Item = T>,
+{
+    new_markers(stream, distinct, false)
+}
+
+/// Markers that don't overlap, the search starting over after each one
+pub fn boundaries<T, I>(stream: I, distinct: usize) -> Markers<T, I::IntoIter>
+where
+    I: IntoIterator<Item = T>,
+{
+    new_markers(stream, distinct, true)
+}
+
+/// Payload following a start-of-packet marker
+#[derive(Debug, PartialEq, Eq)]
+pub struct Packet<'a, T> {
+    /// Position of the first payload item, right after the marker
+    pub offset: usize,
+    pub payload: &'a [T],
+}
+
+/// Splits data on its start-of-packet markers, yielding what lies between the
+/// end of a marker and the start of the next one. Anything before the first
+/// marker isn't part of a packet and is left out.
+pub struct Packets<'a, T> {
+    data: &'a [T],
+    distinct: usize,
+    boundaries: Markers<&'a T, std::slice::Iter<'a, T>>,
+    next_offset: Option<usize>,
+}
+
+impl<'a, T: Eq + Hash> Iterator for Packets<'a, T> {
+    type Item = Packet<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.next_offset?;
+        self.next_offset = self.boundaries.next();
+        let end = self
+            .next_offset
+            .map_or(self.data.len(), |n| n - self.distinct);
+        Some(Packet {
+            offset,
+            payload: &self.data[offset..end],
+        })
+    }
+}
+
+pub fn packets<T: Eq + Hash>(data: &[T], distinct: usize) -> Packets<'_, T> {
+    let mut boundaries = boundaries(data.iter(), distinct);
+    let next_offset = boundaries.next();
+    Packets {
+        data,
+        distinct,
+        boundaries,
+        next_offset,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markers::*;
+
+    #[test]
+    fn markers_test() {
+        let all: Vec<usize> = markers("aabcdefa".chars(), 4).collect();
+        assert_eq!(vec![5, 6, 7, 8], all);
+        let disjoint: Vec<usize> = boundaries("aabcdefa".chars(), 4).collect();
+        assert_eq!(vec![5], disjoint);
+        let disjoint: Vec<usize> = boundaries("abcdabcdxabcd".chars(), 4).collect();
+        assert_eq!(vec![4, 8, 12], disjoint);
+        assert_eq!(None, markers("abc".chars(), 4).next());
+    }
+
+    #[test]
+    fn packets_test() {
+        let data = b"aaaabcdaabbaaabcdxxyyxxxyzw";
+        let split: Vec<(usize, &[u8])> = packets(data, 4).map(|p| (p.offset, p.payload)).collect();
+        assert_eq!(
+            vec![(7, &b"aabbaa"[..]), (17, &b"xxyyxx"[..]), (27, &b""[..])],
+            split
+        );
+        assert_eq!(0, packets(b"aaaa", 4).count());
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::predicate::{find, Predicate};
+
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Same result as `find`, with the data split into chunks scanned by
+/// `threads` worker threads. Chunks overlap by one window minus one byte so
+/// that no window is missed, and are handed out in order; a worker skips the
+/// chunks starting after the earliest marker found so far.
+pub fn find_parallel(data: &[u8], predicate: &dyn Predicate, threads: usize) -> Option<usize> {
+    search(data, predicate, threads, CHUNK_SIZE)
+}
+
+fn search(
+    data: &[u8],
+    predicate: &dyn Predicate,
+    threads: usize,
+    chunk_size: usize,
+) -> Option<usize> {
+    let overlap = predicate.window().saturating_sub(1);
+    if predicate.window() == 0 || data.len() <= chunk_size || threads <= 1 {
+        return find(data, predicate);
+    }
+    let chunks = data.len().div_ceil(chunk_size);
+    let next_chunk = AtomicUsize::new(0);
+    let earliest = AtomicUsize::new(usize::MAX);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.min(chunks) {
+            scope.spawn(|| loop {
+                let chunk = next_chunk.fetch_add(1, Ordering::Relaxed);
+                let start = chunk * chunk_size;
+                if chunk >= chunks || start >= earliest.load(Ordering::Relaxed) {
+                    break;
+                }
+                let end = (start + chunk_size + overlap).min(data.len());
+                if let Some(marker) = find(&data[start..end], predicate) {
+                    earliest.fetch_min(start + marker, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    let earliest = earliest.into_inner();
+    (earliest != usize::MAX).then_some(earliest)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::random_buffer;
+    use crate::parallel::*;
+    use crate::predicate::{AtLeast, Distinct, Pattern};
+
+    #[test]
+    fn parallel_test() {
+        for (alphabet, seed) in [(14, 1), (16, 2), (18, 3), (26, 4), (13, 5)] {
+            let data = random_buffer(200_000, alphabet, seed).into_bytes();
+            let predicates: [&dyn Predicate; 3] = [
+                &Distinct(14),
+                &AtLeast {
+                    min: 12,
+                    window: 13,
+                },
+                &Pattern(data[150_000..150_020].to_vec()),
+            ];
+            for predicate in predicates {
+                let sequential = find(&data, predicate);
+                for (threads, chunk_size) in [(2, 1_000), (4, 4_096), (8, 777), (3, 13)] {
+                    assert_eq!(sequential, search(&data, predicate, threads, chunk_size));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+/// Byte counts of the current window, kept up to date while it slides
+pub struct Counts {
+    counts: [usize; 256],
+    /// Number of different bytes in the window
+    pub distinct: usize,
+}
+
+impl Counts {
+    fn new() -> Self {
+        Counts {
+            counts: [0; 256],
+            distinct: 0,
+        }
+    }
+
+    fn add(&mut self, byte: u8) {
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] == 1 {
+            self.distinct += 1;
+        }
+    }
+
+    fn remove(&mut self, byte: u8) {
+        self.counts[byte as usize] -= 1;
+        if self.counts[byte as usize] == 0 {
+            self.distinct -= 1;
+        }
+    }
+}
+
+/// What makes a window of the datastream a marker. Predicates are shared
+/// between the threads of a parallel search.
+pub trait Predicate: Sync {
+    /// Number of bytes in a window
+    fn window(&self) -> usize;
+    /// Whether `window`, whose bytes are counted in `counts`, is a marker
+    fn matches(&self, window: &[u8], counts: &Counts) -> bool;
+}
+
+/// `k` pairwise different bytes, the puzzle's markers
+pub struct Distinct(pub usize);
+
+impl Predicate for Distinct {
+    fn window(&self) -> usize {
+        self.0
+    }
+
+    fn matches(&self, _: &[u8], counts: &Counts) -> bool {
+        counts.distinct == self.0
+    }
+}
+
+/// At least `min` different bytes among `window`
+pub struct AtLeast {
+    pub min: usize,
+    pub window: usize,
+}
+
+impl Predicate for AtLeast {
+    fn window(&self) -> usize {
+        self.window
+    }
+
+    fn matches(&self, _: &[u8], counts: &Counts) -> bool {
+        counts.distinct >= self.min
+    }
+}
+
+/// `k` pairwise different bytes all taken from an alphabet
+pub struct Alphabet {
+    pub window: usize,
+    pub allowed: [bool; 256],
+}
+
+impl Alphabet {
+    pub fn new(window: usize, alphabet: &[u8]) -> Self {
+        let mut allowed = [false; 256];
+        for byte in alphabet.iter() {
+            allowed[*byte as usize] = true;
+        }
+        Alphabet { window, allowed }
+    }
+}
+
+impl Predicate for Alphabet {
+    fn window(&self) -> usize {
+        self.window
+    }
+
+    fn matches(&self, window: &[u8], counts: &Counts) -> bool {
+        counts.distinct == self.window && window.iter().all(|b| self.allowed[*b as usize])
+    }
+}
+
+/// Exactly the given bytes
+pub struct Pattern(pub Vec<u8>);
+
+impl Predicate for Pattern {
+    fn window(&self) -> usize {
+        self.0.len()
+    }
+
+    fn matches(&self, window: &[u8], _: &Counts) -> bool {
+        window == self.0
+    }
+}
+
+/// Number of bytes read up to the end of the first window matching `predicate`
+pub fn find(data: &[u8], predicate: &dyn Predicate) -> Option<usize> {
+    let size = predicate.window();
+    let mut counts = Counts::new();
+    if size == 0 {
+        return predicate.matches(&[], &counts).then_some(0);
+    }
+    for (i, byte) in data.iter().enumerate() {
+        counts.add(*byte);
+        if i >= size {
+            counts.remove(data[i - size]);
+        }
+        if i + 1 >= size && predicate.matches(&data[i + 1 - size..=i], &counts) {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// Predicate from its CLI name: `distinct:K`, `at-least:M/K`,
+/// `alphabet:K:LETTERS` or `pattern:BYTES`
+pub fn predicate_by_name(name: &str) -> Option<Box<dyn Predicate>> {
+    let number = |n: &str| n.parse::<usize>().ok();
+    match name.split_once(':')? {
+        ("distinct", k) => Some(Box::new(Distinct(number(k)?))),
+        ("at-least", spec) => {
+            let (min, window) = spec.split_once('/')?;
+            let (min, window) = (number(min)?, number(window)?);
+            (min <= window).then(|| Box::new(AtLeast { min, window }) as Box<dyn Predicate>)
+        }
+        ("alphabet", spec) => {
+            let (k, letters) = spec.split_once(':')?;
+            Some(Box::new(Alphabet::new(number(k)?, letters.as_bytes())))
+        }
+        ("pattern", bytes) => Some(Box::new(Pattern(bytes.as_bytes().to_vec()))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::random_buffer;
+    use crate::check_marker_bytes;
+    use crate::predicate::*;
+
+    #[test]
+    fn distinct_test() {
+        let input = random_buffer(20_000, 24, 8).into_bytes();
+        for k in [1, 4, 14, 20] {
+            assert_eq!(check_marker_bytes(&input, k), find(&input, &Distinct(k)));
+        }
+        assert_eq!(None, find(b"abc", &Distinct(4)));
+    }
+
+    #[test]
+    fn predicates_test() {
+        let data = b"aabbabcacdxyzw";
+        assert_eq!(Some(7), find(data, &AtLeast { min: 3, window: 4 }));
+        assert_eq!(Some(3), find(data, &AtLeast { min: 2, window: 3 }));
+        assert_eq!(Some(7), find(data, &Distinct(3)));
+        assert_eq!(Some(12), find(data, &Alphabet::new(3, b"dxyz")));
+        assert_eq!(Some(9), find(data, &Pattern(b"cac".to_vec())));
+        assert_eq!(None, find(data, &Pattern(b"ccc".to_vec())));
+    }
+
+    #[test]
+    fn predicate_by_name_test() {
+        let data = b"aabbabcacdxyzw";
+        let found = |name: &str| find(data, predicate_by_name(name).unwrap().as_ref());
+        assert_eq!(Some(7), found("at-least:3/4"));
+        assert_eq!(Some(12), found("alphabet:3:dxyz"));
+        assert_eq!(Some(9), found("pattern:cac"));
+        assert!(predicate_by_name("at-least:5/4").is_none());
+        assert!(predicate_by_name("distinct").is_none());
+        assert!(predicate_by_name("palindrome:4").is_none());
+    }
+}
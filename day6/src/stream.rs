@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read};
+
+const CHUNK_SIZE: usize = 4096;
+
+/// Sliding window over a byte stream, keeping only the last position of
+/// every byte value, so its size doesn't depend on the stream length
+struct Window {
+    distinct: usize,
+    /// Position + 1 of the last occurrence of each byte, 0 if never seen
+    last_seen: [usize; 256],
+    start: usize,
+    found: bool,
+}
+
+impl Window {
+    fn new(distinct: usize) -> Self {
+        Window {
+            distinct,
+            last_seen: [0; 256],
+            start: 0,
+            found: false,
+        }
+    }
+
+    /// Take the byte at `position`, telling whether it completes the marker
+    fn push(&mut self, byte: u8, position: usize) -> bool {
+        let previous = self.last_seen[byte as usize];
+        self.last_seen[byte as usize] = position + 1;
+        self.start = self.start.max(previous);
+        position + 1 - self.start == self.distinct
+    }
+}
+
+/// Marker found in a stream, `offset` being the number of bytes read up to
+/// its end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Marker {
+    pub distinct: usize,
+    pub offset: usize,
+}
+
+/// Reads any source chunk by chunk, yielding the first marker of every
+/// window size as soon as it is read. Reading stops once every marker has
+/// been found, so the source can be endless.
+pub struct Scanner<R: Read> {
+    reader: R,
+    chunk: Box<[u8; CHUNK_SIZE]>,
+    /// Bytes of `chunk` that are filled, and how many have been scanned
+    filled: usize,
+    scanned: usize,
+    offset: usize,
+    windows: Vec<Window>,
+    /// Markers completed by the same byte wait here
+    pending: VecDeque<Marker>,
+}
+
+impl<R: Read> Scanner<R> {
+    pub fn new(reader: R, sizes: &[usize]) -> Self {
+        let mut windows: Vec<Window> = sizes.iter().map(|d| Window::new(*d)).collect();
+        let mut pending: VecDeque<Marker> = VecDeque::new();
+        for window in windows.iter_mut().filter(|w| w.distinct == 0) {
+            window.found = true;
+            pending.push_back(Marker {
+                distinct: 0,
+                offset: 0,
+            });
+        }
+        Scanner {
+            reader,
+            chunk: Box::new([0; CHUNK_SIZE]),
+            filled: 0,
+            scanned: 0,
+            offset: 0,
+            windows,
+            pending,
+        }
+    }
+}
+
+impl<R: Read> Iterator for Scanner<R> {
+    type Item = io::Result<Marker>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && self.windows.iter().any(|w| !w.found) {
+            if self.scanned == self.filled {
+                match self.reader.read(&mut self.chunk[..]) {
+                    Ok(0) => return None,
+                    Ok(n) => {
+                        self.filled = n;
+                        self.scanned = 0;
+                    }
+                    Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                    Err(error) => return Some(Err(error)),
+                }
+                continue;
+            }
+            let byte = self.chunk[self.scanned];
+            for window in self.windows.iter_mut().filter(|w| !w.found) {
+                if window.push(byte, self.offset) {
+                    window.found = true;
+                    self.pending.push_back(Marker {
+                        distinct: window.distinct,
+                        offset: self.offset + 1,
+                    });
+                }
+            }
+            self.scanned += 1;
+            self.offset += 1;
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::random_buffer;
+    use crate::check_marker_bytes;
+    use crate::stream::*;
+
+    /// Hands out at most `step` bytes per read, like a slow pipe
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    /// Never ends, repeating its pattern forever
+    struct Endless(&'static [u8]);
+
+    impl Read for Endless {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            for (i, b) in buf.iter_mut().enumerate() {
+                *b = self.0[i % self.0.len()];
+            }
+            Ok(buf.len() - buf.len() % self.0.len())
+        }
+    }
+
+    #[test]
+    fn scanner_test() {
+        let input = random_buffer(20_000, 20, 6).into_bytes();
+        for step in [1, 7, CHUNK_SIZE] {
+            let reader = Trickle { data: &input, step };
+            let markers: Vec<Marker> = Scanner::new(reader, &[14, 4, 1000])
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(
+                vec![
+                    Marker {
+                        distinct: 4,
+                        offset: check_marker_bytes(&input, 4).unwrap()
+                    },
+                    Marker {
+                        distinct: 14,
+                        offset: check_marker_bytes(&input, 14).unwrap()
+                    },
+                ],
+                markers
+            );
+        }
+    }
+
+    #[test]
+    fn endless_test() {
+        let mut scanner = Scanner::new(Endless(b"aabcd"), &[0, 2, 4]);
+        let offsets: Vec<usize> = scanner.by_ref().map(|m| m.unwrap().offset).collect();
+        assert_eq!(vec![0, 3, 5], offsets);
+        assert!(scanner.next().is_none());
+
+        let mut same_byte = Scanner::new(&b"abcd"[..], &[4, 4]);
+        assert_eq!(4, same_byte.next().unwrap().unwrap().offset);
+        assert_eq!(4, same_byte.next().unwrap().unwrap().offset);
+        assert!(same_byte.next().is_none());
+    }
+}
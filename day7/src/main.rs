@@ -1,31 +1,113 @@
-use std::cell::RefCell;
+use std::cell::OnceCell;
 use std::collections::{HashMap, VecDeque};
-use std::fs;
-use std::rc::{Rc, Weak};
+use std::ops::RangeInclusive;
+use std::{env, fmt, fs, process};
 
-#[derive(Debug)]
+mod query;
+
+/// Index of a directory in the tree's arena
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct NodeId(usize);
+
+/// Directories live in one vector and refer to each other by index
+#[derive(Debug, Clone)]
 struct DirectoryTree {
-    root: Rc<RefCell<Node>>,
+    nodes: Vec<Node>,
+    /// Total size of every directory, dropped whenever the tree changes
+    sizes: OnceCell<Vec<usize>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Node {
     name: String,
-    parent: Option<Weak<RefCell<Node>>>,
-    directories: Vec<Rc<RefCell<Node>>>,
+    parent: Option<NodeId>,
+    directories: Vec<NodeId>,
     files: Vec<File>,
+    /// Positions in `directories` and `files` by name, so lookups while
+    /// replaying the log don't scan the whole directory
+    directory_index: HashMap<String, NodeId>,
+    file_index: HashMap<String, usize>,
 }
 
 impl DirectoryTree {
+    const ROOT: NodeId = NodeId(0);
+
     fn new() -> Self {
         DirectoryTree {
-            root: Rc::new(RefCell::new(Node::new("/"))),
+            nodes: vec![Node::new("/")],
+            sizes: OnceCell::new(),
         }
     }
+
+    fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.sizes.take();
+        &mut self.nodes[id.0]
+    }
+
+    fn add_dir(&mut self, parent: NodeId, name: &str) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        let mut node = Node::new(name);
+        node.parent = Some(parent);
+        self.nodes.push(node);
+        let parent = self.node_mut(parent);
+        parent.directories.push(id);
+        parent.directory_index.insert(name.to_string(), id);
+        id
+    }
+
+    fn get_parent(&self, dir: NodeId) -> Option<NodeId> {
+        self.node(dir).parent
+    }
+
+    fn find_dir(&self, parent: NodeId, name: &str) -> Option<NodeId> {
+        self.node(parent).directory_index.get(name).copied()
+    }
+
+    /// Index of the file in its directory
+    fn find_file(&self, parent: NodeId, name: &str) -> Option<usize> {
+        self.node(parent).file_index.get(name).copied()
+    }
+
+    fn get_size(&self, dir: NodeId) -> usize {
+        self.sizes.get_or_init(|| self.compute_sizes())[dir.0]
+    }
+
+    /// Sizes of all directories in one pass. A directory is always added
+    /// after its parent, so going through the arena backwards reaches every
+    /// directory once all of its subdirectories are counted.
+    fn compute_sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self
+            .nodes
+            .iter()
+            .map(|n| n.files.iter().map(|f| f.size).sum())
+            .collect();
+        for (id, node) in self.nodes.iter().enumerate().skip(1).rev() {
+            sizes[node.parent.unwrap().0] += sizes[id];
+        }
+        sizes
+    }
+
+    fn get_full_path(&self, dir: NodeId) -> String {
+        let mut names: Vec<&str> = Vec::new();
+        let mut current = Some(dir);
+        while let Some(id) = current {
+            names.push(&self.node(id).name);
+            current = self.get_parent(id);
+        }
+        names.reverse();
+        let mut path = names[0].to_string();
+        for name in names.iter().skip(1) {
+            path = path + name + "/";
+        }
+        path
+    }
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Debug, Clone)]
 struct File {
     name: String,
     size: usize,
@@ -47,123 +129,237 @@ impl Node {
             directories: vec![],
             parent: None,
             files: vec![],
+            directory_index: HashMap::new(),
+            file_index: HashMap::new(),
         }
     }
 
-    fn add_dir(parent: &Rc<RefCell<Node>>, name: &str) {
-        let new_node = Rc::new(RefCell::new(Node::new(name)));
-        new_node.borrow_mut().parent = Some(Rc::downgrade(&parent));
-        parent.borrow_mut().directories.push(new_node);
+    fn add_file(&mut self, file: File) {
+        self.file_index.insert(file.name.clone(), self.files.len());
+        self.files.push(file);
     }
+}
 
-    fn get_parent(dir: &Node) -> Option<Rc<RefCell<Node>>> {
-        match &dir.parent {
-            Some(parent) => parent.upgrade(),
-            None => None,
-        }
-    }
-    fn get_size(dir: &Node) -> usize {
-        let mut size = 0;
-        for f in dir.files.iter() {
-            size += f.size;
-        }
-        for d in dir.directories.iter() {
-            size += Self::get_size(&d.borrow());
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Reject `cd` into a directory that wasn't listed
+    Strict,
+    /// Create directories on `cd` when they weren't listed
+    Lenient,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ShellError {
+    UnknownDirectory {
+        line: usize,
+        path: String,
+    },
+    UnknownCommand {
+        line: usize,
+        command: String,
+    },
+    /// Line that is neither a command nor `ls` output
+    Malformed {
+        line: usize,
+        text: String,
+    },
+}
+
+impl fmt::Display for ShellError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShellError::UnknownDirectory { line, path } => {
+                write!(f, "line {}: cd: no such directory '{}'", line, path)
+            }
+            ShellError::UnknownCommand { line, command } => {
+                write!(f, "line {}: unknown command '{}'", line, command)
+            }
+            ShellError::Malformed { line, text } => {
+                write!(f, "line {}: can't read '{}'", line, text)
+            }
         }
-        size
     }
+}
 
-    fn get_full_path(dir: &Node) -> String {
-        let mut path = dir.name.to_string();
-        match dir.parent.as_ref() {
-            Some(p) => path = Self::get_full_path(&p.upgrade().unwrap().borrow()) + &path + "/",
-            None => (),
+#[derive(Debug, PartialEq, Eq)]
+enum ConflictKind {
+    /// The latest size is kept
+    Size { before: usize, after: usize },
+    /// The name is already taken by an entry of the other kind, which is kept
+    FileAndDirectory,
+}
+
+/// Listing that disagrees with an earlier listing of the same directory
+#[derive(Debug, PartialEq, Eq)]
+struct Conflict {
+    line: usize,
+    path: String,
+    kind: ConflictKind,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ConflictKind::Size { before, after } => write!(
+                f,
+                "line {}: {} listed with size {} after {}, keeping {}",
+                self.line, self.path, after, before, after
+            ),
+            ConflictKind::FileAndDirectory => write!(
+                f,
+                "line {}: {} listed both as a file and as a directory, keeping the first",
+                self.line, self.path
+            ),
         }
-        path
     }
 }
 
-/// Change directory
-fn cd(path: &str, tree_ptr: &mut Rc<RefCell<Node>>, filetree: &DirectoryTree) {
+/// Change directory, returning false when the directory doesn't exist
+fn cd(path: &str, tree_ptr: &mut NodeId, filetree: &mut DirectoryTree, mode: Mode) -> bool {
     match path {
-        "/" => *tree_ptr = Rc::clone(&filetree.root),
+        "/" => *tree_ptr = DirectoryTree::ROOT,
         ".." => {
-            let p = match Node::get_parent(&tree_ptr.borrow()) {
-                Some(directory) => Rc::clone(&directory),
-                None => Rc::clone(&filetree.root),
-            };
-            *tree_ptr = p;
+            *tree_ptr = filetree
+                .get_parent(*tree_ptr)
+                .unwrap_or(DirectoryTree::ROOT)
         }
-        dir => {
-            let mut val: Rc<RefCell<Node>> = Rc::clone(&filetree.root);
-            for d in tree_ptr.borrow_mut().directories.iter() {
-                if d.borrow_mut().name == dir {
-                    val = d.clone();
-                }
+        dir => match (filetree.find_dir(*tree_ptr, dir), mode) {
+            (Some(d), _) => *tree_ptr = d,
+            (None, Mode::Lenient) if filetree.find_file(*tree_ptr, dir).is_none() => {
+                *tree_ptr = filetree.add_dir(*tree_ptr, dir)
             }
-            *tree_ptr = Rc::clone(&val);
-        }
+            (None, _) => return false,
+        },
     }
+    true
 }
 
-/// Make directory
-fn mkdir(name: &str, tree_ptr: &mut Rc<RefCell<Node>>) {
-    Node::add_dir(tree_ptr, name);
+/// Make directory, unless it already exists
+fn mkdir(name: &str, tree_ptr: NodeId, filetree: &mut DirectoryTree) -> Result<(), ConflictKind> {
+    if filetree.find_file(tree_ptr, name).is_some() {
+        return Err(ConflictKind::FileAndDirectory);
+    }
+    if filetree.find_dir(tree_ptr, name).is_none() {
+        filetree.add_dir(tree_ptr, name);
+    }
+    Ok(())
 }
 
-/// Create file
-fn touch(name: &str, size: usize, tree_ptr: &mut Rc<RefCell<Node>>) {
-    tree_ptr.borrow_mut().files.push(File::new(name, size));
+/// Create file, or update its size if it already exists
+fn touch(
+    name: &str,
+    size: usize,
+    tree_ptr: NodeId,
+    filetree: &mut DirectoryTree,
+) -> Result<(), ConflictKind> {
+    if filetree.find_dir(tree_ptr, name).is_some() {
+        return Err(ConflictKind::FileAndDirectory);
+    }
+    match filetree.find_file(tree_ptr, name) {
+        Some(i) if filetree.node(tree_ptr).files[i].size == size => Ok(()),
+        Some(i) => {
+            let file = &mut filetree.node_mut(tree_ptr).files[i];
+            let before = file.size;
+            file.size = size;
+            Err(ConflictKind::Size {
+                before,
+                after: size,
+            })
+        }
+        None => {
+            filetree.node_mut(tree_ptr).add_file(File::new(name, size));
+            Ok(())
+        }
+    }
 }
 
-fn map_sizes(dir: &Node, h: &mut HashMap<String, usize>) {
-    let size = Node::get_size(dir);
-    h.insert(Node::get_full_path(dir), size);
-    for d in dir.directories.iter() {
-        map_sizes(&d.borrow(), h);
+/// Size of every directory under `dir` by full path, building each path
+/// from its parent's
+fn map_sizes(filetree: &DirectoryTree, dir: NodeId, h: &mut HashMap<String, usize>) {
+    let mut stack: Vec<(NodeId, String)> = vec![(dir, filetree.get_full_path(dir))];
+    while let Some((id, path)) = stack.pop() {
+        for d in filetree.node(id).directories.iter().rev() {
+            stack.push((*d, path.clone() + &filetree.node(*d).name + "/"));
+        }
+        h.insert(path, filetree.get_size(id));
     }
 }
 
-fn initialise_filetree(input: &str, filetree: &DirectoryTree) {
-    let mut tree_ptr: Rc<RefCell<Node>> = Rc::clone(&filetree.root);
+/// Replay a terminal log. Listing a directory again merges with what is
+/// already known, and disagreements are returned as conflicts.
+fn initialise_filetree(
+    input: &str,
+    filetree: &mut DirectoryTree,
+    mode: Mode,
+) -> Result<Vec<Conflict>, ShellError> {
+    let mut tree_ptr = DirectoryTree::ROOT;
+    let mut conflicts: Vec<Conflict> = Vec::new();
 
     let mut lines = input
         .lines()
-        .map(|l| l.split_whitespace().collect())
-        .collect::<VecDeque<Vec<&str>>>();
-
-    while let Some(command) = lines.pop_front() {
-        match command[1] {
-            "cd" => {
-                let path = command[2];
-                cd(path, &mut tree_ptr, &filetree);
+        .enumerate()
+        .map(|(i, l)| (i + 1, l, l.split_whitespace().collect()))
+        .filter(|(_, _, words): &(usize, &str, Vec<&str>)| !words.is_empty())
+        .collect::<VecDeque<(usize, &str, Vec<&str>)>>();
+
+    while let Some((line, text, command)) = lines.pop_front() {
+        let malformed = || ShellError::Malformed {
+            line,
+            text: text.to_string(),
+        };
+        if command[0] != "$" {
+            return Err(malformed());
+        }
+        match command.get(1).copied() {
+            Some("cd") => {
+                let path = *command.get(2).ok_or_else(malformed)?;
+                if !cd(path, &mut tree_ptr, filetree, mode) {
+                    return Err(ShellError::UnknownDirectory {
+                        line,
+                        path: path.to_string(),
+                    });
+                }
             }
-            "ls" => {
-                while let Some(output) = lines.pop_front() {
+            Some("ls") => {
+                while let Some((line, text, output)) = lines.pop_front() {
                     if output[0] == "$" {
-                        lines.push_front(output);
+                        lines.push_front((line, text, output));
                         break;
                     }
-                    if output[0] == "dir" {
-                        let name = output[1];
-                        mkdir(name, &mut tree_ptr);
-                    } else if output[0].chars().all(char::is_numeric) {
-                        let name = output[1];
-                        let size = output[0].parse::<usize>().unwrap();
-                        touch(name, size, &mut tree_ptr);
+                    let malformed = || ShellError::Malformed {
+                        line,
+                        text: text.to_string(),
+                    };
+                    let name = *output.get(1).ok_or_else(malformed)?;
+                    let result = if output[0] == "dir" {
+                        mkdir(name, tree_ptr, filetree)
+                    } else {
+                        let size = output[0].parse::<usize>().map_err(|_| malformed())?;
+                        touch(name, size, tree_ptr, filetree)
+                    };
+                    if let Err(kind) = result {
+                        conflicts.push(Conflict {
+                            line,
+                            path: filetree.get_full_path(tree_ptr) + name,
+                            kind,
+                        });
                     }
                 }
             }
-            _ => unreachable!(),
+            Some(other) => {
+                return Err(ShellError::UnknownCommand {
+                    line,
+                    command: other.to_string(),
+                })
+            }
+            None => return Err(malformed()),
         }
     }
+    Ok(conflicts)
 }
 
 fn part1(dir_map: &HashMap<String, usize>) -> usize {
-    dir_map
-        .values()
-        .filter(|n| *n <= &(100_000 as usize))
-        .sum::<usize>()
+    dir_map.values().filter(|n| **n <= 100_000).sum::<usize>()
 }
 
 fn part2(dir_map: &HashMap<String, usize>) -> usize {
@@ -174,14 +370,103 @@ fn part2(dir_map: &HashMap<String, usize>) -> usize {
         .unwrap()
 }
 
+fn option<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Size range written `MIN..MAX`, either bound being optional
+fn parse_size_range(range: &str) -> Option<RangeInclusive<usize>> {
+    let (min, max) = range.split_once("..")?;
+    let min = if min.is_empty() { 0 } else { min.parse().ok()? };
+    let max = if max.is_empty() {
+        usize::MAX
+    } else {
+        max.parse().ok()?
+    };
+    Some(min..=max)
+}
+
+/// `--name GLOB`, `--size MIN..MAX` and `--type f|d`
+fn parse_query(args: &[String]) -> query::Query {
+    let size = option(args, "--size").map(|r| {
+        parse_size_range(r).unwrap_or_else(|| {
+            eprintln!("--size takes a range MIN..MAX, either bound being optional");
+            process::exit(1);
+        })
+    });
+    let kind = option(args, "--type").map(|t| match t {
+        "f" => query::Kind::File,
+        "d" => query::Kind::Directory,
+        _ => {
+            eprintln!("--type takes f or d");
+            process::exit(1);
+        }
+    });
+    query::Query {
+        name: option(args, "--name").map(str::to_string),
+        size,
+        kind,
+    }
+}
+
 fn main() {
-    let filetree = DirectoryTree::new();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mode = if args.iter().any(|a| a == "--lenient") {
+        Mode::Lenient
+    } else {
+        Mode::Strict
+    };
+    let mut filetree = DirectoryTree::new();
 
     let input = fs::read_to_string("input.txt").unwrap();
-    initialise_filetree(&input, &filetree);
+    match initialise_filetree(&input, &mut filetree, mode) {
+        Ok(conflicts) => conflicts.iter().for_each(|c| eprintln!("{}", c)),
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+
+    match args.first().map(String::as_str) {
+        Some("tree") => {
+            print!("{}", query::tree(&filetree));
+            return;
+        }
+        Some("du") => {
+            let depth = option(&args, "-d").map_or(Ok(usize::MAX), str::parse::<usize>);
+            let Ok(depth) = depth else {
+                eprintln!("-d takes a depth");
+                process::exit(1);
+            };
+            for (path, size) in query::du(&filetree, depth) {
+                println!("{}\t{}", size, path);
+            }
+            return;
+        }
+        Some("find") => {
+            for entry in query::find(&filetree, &parse_query(&args)) {
+                println!("{}", entry);
+            }
+            return;
+        }
+        Some("largest") => {
+            let n = args
+                .get(1)
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or(10);
+            for entry in query::largest(&filetree, n, parse_query(&args).kind) {
+                println!("{}", entry);
+            }
+            return;
+        }
+        _ => {}
+    }
 
     let mut dir_map: HashMap<String, usize> = HashMap::new();
-    map_sizes(&filetree.root.borrow(), &mut dir_map);
+    map_sizes(&filetree, DirectoryTree::ROOT, &mut dir_map);
 
     println!("Part 1: {}", part1(&dir_map));
     println!("Part 2: {}", part2(&dir_map));
@@ -192,21 +477,137 @@ mod tests {
     use crate::*;
     #[test]
     fn part1_test() {
-        let filetree = DirectoryTree::new();
+        let mut filetree = DirectoryTree::new();
         let input = fs::read_to_string("test.txt").unwrap();
-        initialise_filetree(&input, &filetree);
+        initialise_filetree(&input, &mut filetree, Mode::Strict).unwrap();
         let mut dir_map: HashMap<String, usize> = HashMap::new();
-        map_sizes(&filetree.root.borrow(), &mut dir_map);
+        map_sizes(&filetree, DirectoryTree::ROOT, &mut dir_map);
         assert_eq!(95437, part1(&dir_map));
     }
 
     #[test]
     fn part2_test() {
-        let filetree = DirectoryTree::new();
+        let mut filetree = DirectoryTree::new();
         let input = fs::read_to_string("test.txt").unwrap();
-        initialise_filetree(&input, &filetree);
+        initialise_filetree(&input, &mut filetree, Mode::Strict).unwrap();
         let mut dir_map: HashMap<String, usize> = HashMap::new();
-        map_sizes(&filetree.root.borrow(), &mut dir_map);
+        map_sizes(&filetree, DirectoryTree::ROOT, &mut dir_map);
         assert_eq!(24933642, part2(&dir_map));
     }
+
+    #[test]
+    fn arena_test() {
+        fn assert_send<T: Send>() {}
+        assert_send::<DirectoryTree>();
+
+        let mut filetree = DirectoryTree::new();
+        let input = fs::read_to_string("test.txt").unwrap();
+        initialise_filetree(&input, &mut filetree, Mode::Strict).unwrap();
+        assert_eq!(4, filetree.nodes.len());
+        let mut dir = DirectoryTree::ROOT;
+        assert!(cd("a", &mut dir, &mut filetree, Mode::Strict));
+        assert!(cd("e", &mut dir, &mut filetree, Mode::Strict));
+        assert_eq!("/a/e/", filetree.get_full_path(dir));
+        assert_eq!(584, filetree.get_size(dir));
+        assert!(cd("..", &mut dir, &mut filetree, Mode::Strict));
+        assert_eq!(94853, filetree.get_size(dir));
+        assert!(cd("/", &mut dir, &mut filetree, Mode::Strict));
+        assert!(cd("..", &mut dir, &mut filetree, Mode::Strict));
+        assert_eq!(DirectoryTree::ROOT, dir);
+    }
+
+    #[test]
+    fn sizes_test() {
+        let mut filetree = DirectoryTree::new();
+        let mut dir = DirectoryTree::ROOT;
+        for depth in 0..200_000 {
+            touch("f", 1, dir, &mut filetree).unwrap();
+            dir = filetree.add_dir(dir, &depth.to_string());
+        }
+        assert_eq!(200_000, filetree.get_size(DirectoryTree::ROOT));
+        assert_eq!(0, filetree.get_size(dir));
+        touch("g", 5, dir, &mut filetree).unwrap();
+        assert_eq!(200_005, filetree.get_size(DirectoryTree::ROOT));
+
+        let mut filetree = DirectoryTree::new();
+        for i in 0..100_000 {
+            let dir = filetree.add_dir(DirectoryTree::ROOT, &i.to_string());
+            touch("f", i, dir, &mut filetree).unwrap();
+        }
+        let mut dir_map: HashMap<String, usize> = HashMap::new();
+        map_sizes(&filetree, DirectoryTree::ROOT, &mut dir_map);
+        assert_eq!(100_001, dir_map.len());
+        assert_eq!(Some(&99_999), dir_map.get("/99999/"));
+    }
+
+    #[test]
+    fn shell_test() {
+        let log = "$ cd /\n$ ls\ndir a\n10 b\n$ ls\ndir a\n10 b\n12 c\n\n$ cd a\n$ ls\n5 d\n$ cd ..\n$ ls\n11 b\n7 a\ndir c\n";
+        let mut filetree = DirectoryTree::new();
+        let conflicts = initialise_filetree(log, &mut filetree, Mode::Strict).unwrap();
+        assert_eq!(
+            vec![
+                Conflict {
+                    line: 15,
+                    path: "/b".to_string(),
+                    kind: ConflictKind::Size {
+                        before: 10,
+                        after: 11
+                    }
+                },
+                Conflict {
+                    line: 16,
+                    path: "/a".to_string(),
+                    kind: ConflictKind::FileAndDirectory
+                },
+                Conflict {
+                    line: 17,
+                    path: "/c".to_string(),
+                    kind: ConflictKind::FileAndDirectory
+                },
+            ],
+            conflicts
+        );
+        assert_eq!(2, filetree.nodes.len());
+        assert_eq!(28, filetree.get_size(DirectoryTree::ROOT));
+
+        let log = "$ cd /\n$ cd x\n$ ls\n3 y\n$ cd ..\n$ cd x\n";
+        assert_eq!(
+            Err(ShellError::UnknownDirectory {
+                line: 2,
+                path: "x".to_string()
+            }),
+            initialise_filetree(log, &mut DirectoryTree::new(), Mode::Strict)
+        );
+        let mut filetree = DirectoryTree::new();
+        assert_eq!(
+            Ok(vec![]),
+            initialise_filetree(log, &mut filetree, Mode::Lenient)
+        );
+        let mut dir_map: HashMap<String, usize> = HashMap::new();
+        map_sizes(&filetree, DirectoryTree::ROOT, &mut dir_map);
+        assert_eq!(Some(&3), dir_map.get("/x/"));
+        assert_eq!(2, dir_map.len());
+
+        assert_eq!(
+            Err(ShellError::UnknownCommand {
+                line: 1,
+                command: "rm".to_string()
+            }),
+            initialise_filetree("$ rm x", &mut DirectoryTree::new(), Mode::Strict)
+        );
+        for listing in ["foo bar", "12", "dir"] {
+            assert_eq!(
+                Err(ShellError::Malformed {
+                    line: 3,
+                    text: listing.to_string()
+                }),
+                initialise_filetree(
+                    &format!("$ cd /\n$ ls\n{}\n", listing),
+                    &mut DirectoryTree::new(),
+                    Mode::Strict
+                )
+            );
+        }
+    }
 }
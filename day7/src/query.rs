@@ -0,0 +1,272 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::{DirectoryTree, NodeId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Directory,
+}
+
+/// File or directory with its full path and size, directories counting
+/// everything below them. Directory paths end with `/`, as in `map_sizes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub path: String,
+    pub size: usize,
+    pub kind: Kind,
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>10}  {}", self.size, self.path)
+    }
+}
+
+/// Every directory and file, each directory before what it holds
+pub fn entries(filetree: &DirectoryTree) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let root = DirectoryTree::ROOT;
+    let mut stack: Vec<(NodeId, String)> = vec![(root, filetree.get_full_path(root))];
+    while let Some((id, path)) = stack.pop() {
+        let node = filetree.node(id);
+        for d in node.directories.iter().rev() {
+            stack.push((*d, path.clone() + &filetree.node(*d).name + "/"));
+        }
+        entries.push(Entry {
+            path: path.clone(),
+            size: filetree.get_size(id),
+            kind: Kind::Directory,
+        });
+        for file in node.files.iter() {
+            entries.push(Entry {
+                path: path.clone() + &file.name,
+                size: file.size,
+                kind: Kind::File,
+            });
+        }
+    }
+    entries
+}
+
+/// Indented listing in the puzzle's format, with directory sizes
+pub fn tree(filetree: &DirectoryTree) -> String {
+    let mut output = String::new();
+    let mut stack: Vec<(NodeId, usize)> = vec![(DirectoryTree::ROOT, 0)];
+    while let Some((id, depth)) = stack.pop() {
+        let node = filetree.node(id);
+        let indent = "  ".repeat(depth);
+        output += &format!(
+            "{}- {} (dir, size={})\n",
+            indent,
+            node.name,
+            filetree.get_size(id)
+        );
+        // Files first, then subdirectories, each sorted by name
+        let mut directories = node.directories.clone();
+        directories.sort_by_key(|d| &filetree.node(*d).name);
+        let mut files: Vec<_> = node.files.iter().collect();
+        files.sort_by_key(|f| &f.name);
+        for file in files.iter() {
+            output += &format!("{}  - {} (file, size={})\n", indent, file.name, file.size);
+        }
+        for d in directories.iter().rev() {
+            stack.push((*d, depth + 1));
+        }
+    }
+    output
+}
+
+/// Directory sizes down to `max_depth` levels below the root, subdirectories
+/// before their parent like `du -d N`
+pub fn du(filetree: &DirectoryTree, max_depth: usize) -> Vec<(String, usize)> {
+    let mut sizes: Vec<(String, usize)> = Vec::new();
+    // A directory is pushed back as visited before its children, so it is
+    // popped again once they have all been listed
+    let mut stack: Vec<(NodeId, String, usize, bool)> =
+        vec![(DirectoryTree::ROOT, "/".to_string(), 0, false)];
+    while let Some((id, path, depth, visited)) = stack.pop() {
+        if visited || depth >= max_depth {
+            sizes.push((path, filetree.get_size(id)));
+            continue;
+        }
+        stack.push((id, path.clone(), depth, true));
+        for d in filetree.node(id).directories.iter().rev() {
+            let child = path.clone() + &filetree.node(*d).name + "/";
+            stack.push((*d, child, depth + 1, false));
+        }
+    }
+    sizes
+}
+
+/// `*` matches any run of characters and `?` any single character
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Last `*` seen and the name position it is matched up to
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((s, matched)) => {
+                    p = s + 1;
+                    n = matched + 1;
+                    star = Some((s, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Criteria for `find`, all optional
+#[derive(Debug, Default)]
+pub struct Query {
+    /// Glob matched against the last path component
+    pub name: Option<String>,
+    pub size: Option<RangeInclusive<usize>>,
+    pub kind: Option<Kind>,
+}
+
+impl Query {
+    fn matches(&self, entry: &Entry) -> bool {
+        let name = entry
+            .path
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or("");
+        self.name
+            .as_ref()
+            .is_none_or(|pattern| glob_match(pattern, name))
+            && self.size.as_ref().is_none_or(|r| r.contains(&entry.size))
+            && self.kind.is_none_or(|k| k == entry.kind)
+    }
+}
+
+pub fn find(filetree: &DirectoryTree, query: &Query) -> Vec<Entry> {
+    entries(filetree)
+        .into_iter()
+        .filter(|e| query.matches(e))
+        .collect()
+}
+
+/// The `n` biggest entries, optionally only files or directories
+pub fn largest(filetree: &DirectoryTree, n: usize, kind: Option<Kind>) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = entries(filetree)
+        .into_iter()
+        .filter(|e| kind.is_none_or(|k| k == e.kind))
+        .collect();
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    entries.truncate(n);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::*;
+    use crate::{initialise_filetree, map_sizes, Mode};
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn test_tree() -> DirectoryTree {
+        let mut filetree = DirectoryTree::new();
+        let input = fs::read_to_string("test.txt").unwrap();
+        initialise_filetree(&input, &mut filetree, Mode::Strict).unwrap();
+        filetree
+    }
+
+    #[test]
+    fn tree_test() {
+        let output = tree(&test_tree());
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(14, lines.len());
+        assert_eq!("- / (dir, size=48381165)", lines[0]);
+        assert_eq!("  - b.txt (file, size=14848514)", lines[1]);
+        assert_eq!("  - a (dir, size=94853)", lines[3]);
+        assert_eq!("      - i (file, size=584)", lines[8]);
+        assert_eq!("  - d (dir, size=24933642)", lines[9]);
+    }
+
+    #[test]
+    fn du_test() {
+        let filetree = test_tree();
+        assert_eq!(vec![("/".to_string(), 48381165)], du(&filetree, 0));
+        assert_eq!(
+            vec![
+                ("/a/".to_string(), 94853),
+                ("/d/".to_string(), 24933642),
+                ("/".to_string(), 48381165)
+            ],
+            du(&filetree, 1)
+        );
+        let mut dir_map: HashMap<String, usize> = HashMap::new();
+        map_sizes(&filetree, DirectoryTree::ROOT, &mut dir_map);
+        let all = du(&filetree, 5);
+        assert_eq!(4, all.len());
+        for (path, size) in all.iter() {
+            assert_eq!(Some(size), dir_map.get(path));
+        }
+    }
+
+    #[test]
+    fn find_test() {
+        let filetree = test_tree();
+        let paths = |query: &Query| -> Vec<String> {
+            find(&filetree, query).into_iter().map(|e| e.path).collect()
+        };
+        let query = Query {
+            name: Some("d.*".to_string()),
+            ..Query::default()
+        };
+        assert_eq!(vec!["/d/d.log", "/d/d.ext"], paths(&query));
+        let query = Query {
+            size: Some(0..=100_000),
+            kind: Some(Kind::Directory),
+            ..Query::default()
+        };
+        assert_eq!(vec!["/a/", "/a/e/"], paths(&query));
+        let query = Query {
+            name: Some("?".to_string()),
+            size: Some(5_000..=usize::MAX),
+            kind: Some(Kind::File),
+        };
+        assert_eq!(vec!["/a/f", "/d/j", "/d/k"], paths(&query));
+
+        assert!(glob_match("*.t?t", "b.txt"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("*a*b", "xxaxxbxx"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn largest_test() {
+        let filetree = test_tree();
+        let top: Vec<(String, usize)> = largest(&filetree, 2, Some(Kind::File))
+            .into_iter()
+            .map(|e| (e.path, e.size))
+            .collect();
+        assert_eq!(
+            vec![
+                ("/b.txt".to_string(), 14848514),
+                ("/c.dat".to_string(), 8504156)
+            ],
+            top
+        );
+        let top = largest(&filetree, 2, None);
+        assert_eq!("/", top[0].path);
+        assert_eq!("/d/", top[1].path);
+        assert_eq!(14, largest(&filetree, 100, None).len());
+    }
+}